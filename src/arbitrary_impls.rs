@@ -0,0 +1,61 @@
+//! Generates buffers with a random capacity, random contents and a random
+//! position of the oldest element in storage.
+
+use crate::arrayringbuffer::ArrayRingBuffer;
+use crate::ringbufferu::RingBufferU;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+const MAX_SPARE: usize = 8; // Free slots added on top of the contents
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for RingBufferU<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let elements: Vec<T> = u.arbitrary()?;
+        let mut rb = RingBufferU::with_capacity(elements.len() + u.int_in_range(0..=MAX_SPARE)?);
+        if rb.capacity() > 0 {
+            rb.set_start(u.choose_index(rb.capacity())?);
+        }
+        rb.extend(elements);
+        Ok(rb)
+    }
+}
+
+impl<'a, T: Arbitrary<'a>, const N: usize> Arbitrary<'a> for ArrayRingBuffer<T, N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut rb = ArrayRingBuffer::new();
+        if N > 0 {
+            rb.set_start(u.choose_index(N)?);
+        }
+        for _ in 0..u.int_in_range(0..=N)? {
+            rb.push(u.arbitrary()?);
+        }
+        Ok(rb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn arbitrary_test() {
+        let mut state = 1u32;
+        let bytes = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect::<Vec<_>>();
+        let mut u = Unstructured::new(&bytes);
+        let mut wrapped = false;
+        for _ in 0..32 {
+            let rb = RingBuffer::<u8>::arbitrary(&mut u).unwrap();
+            assert!(rb.len() <= rb.capacity());
+            let (_, back) = rb.as_slices();
+            wrapped |= !back.is_empty();
+            let array = ArrayRingBuffer::<u8, 4>::arbitrary(&mut u).unwrap();
+            assert!(array.len() <= 4);
+        }
+        assert!(wrapped);
+    }
+}
@@ -0,0 +1,116 @@
+//! Zero-copy archives of ring buffers with `rkyv`.
+//!
+//! An archived buffer stores its capacity and its elements in logical order,
+//! oldest first, as one contiguous run. Once the bytes are mapped they can
+//! be read in place through `ArchivedRingBuffer` without deserializing.
+
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+use rkyv::munge::munge;
+use rkyv::rancor::Fallible;
+use rkyv::ser::{Allocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Archived, Deserialize, Place, Portable, Serialize};
+
+/// The archived form of a `RingBufferU`.
+#[derive(Portable, rkyv::bytecheck::CheckBytes)]
+#[bytecheck(crate = rkyv::bytecheck)]
+#[rkyv(crate = rkyv)]
+#[repr(C)]
+pub struct ArchivedRingBuffer<T> {
+    capacity: Archived<u64>,
+    elements: ArchivedVec<T>,
+}
+
+impl<T> ArchivedRingBuffer<T> {
+    pub fn capacity(&self) -> usize {
+        self.capacity.to_native() as usize
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The elements from oldest to newest.
+    pub fn as_slice(&self) -> &[T] {
+        self.elements.as_slice()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.elements.iter()
+    }
+}
+
+pub struct RingBufferResolver {
+    elements: VecResolver,
+}
+
+impl<T: Archive, S: Storage<T>> Archive for RingBufferU<T, S> {
+    type Archived = ArchivedRingBuffer<T::Archived>;
+    type Resolver = RingBufferResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedRingBuffer { capacity, elements } = out);
+        (self.capacity() as u64).resolve((), capacity);
+        ArchivedVec::resolve_from_len(self.len(), resolver.elements, elements);
+    }
+}
+
+impl<T, S, Ser> Serialize<Ser> for RingBufferU<T, S>
+where
+    T: Serialize<Ser>,
+    S: Storage<T>,
+    Ser: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut Ser) -> Result<Self::Resolver, Ser::Error> {
+        let (front, back) = self.as_slices();
+        let elements = if back.is_empty() {
+            ArchivedVec::<T::Archived>::serialize_from_slice(front, serializer)?
+        } else {
+            ArchivedVec::<T::Archived>::serialize_from_iter::<T, _, _>(self.iter(), serializer)?
+        };
+        Ok(RingBufferResolver { elements })
+    }
+}
+
+impl<T, D> Deserialize<RingBufferU<T>, D> for ArchivedRingBuffer<T::Archived>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<RingBufferU<T>, D::Error> {
+        let mut rb = RingBufferU::with_capacity(self.capacity());
+        for element in self.iter() {
+            rb.push(element.deserialize(deserializer)?);
+        }
+        Ok(rb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ringbuffer::RingBuffer;
+    use rkyv::rancor::Error;
+
+    #[test]
+    fn archive_test() {
+        let mut rb = RingBuffer::with_capacity(3);
+        rb.extend(1..=4i32);
+        let bytes = rkyv::to_bytes::<Error>(&rb).unwrap();
+        let archived = rkyv::access::<ArchivedRingBuffer<Archived<i32>>, Error>(&bytes).unwrap();
+        assert_eq!(archived.capacity(), 3);
+        assert_eq!(
+            archived.iter().map(|x| x.to_native()).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        let back = rkyv::deserialize::<RingBuffer<i32>, Error>(archived).unwrap();
+        assert_eq!(back, rb);
+        assert_eq!(back.capacity(), 3);
+    }
+}
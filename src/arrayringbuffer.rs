@@ -0,0 +1,57 @@
+use crate::ringbufferu::RingBufferU;
+use std::mem::MaybeUninit;
+
+/// A ring buffer with a capacity fixed at compile time, stored inline
+/// without any heap allocation.
+pub type ArrayRingBuffer<T, const N: usize> = RingBufferU<T, [MaybeUninit<T>; N]>;
+
+impl<T, const N: usize> RingBufferU<T, [MaybeUninit<T>; N]> {
+    pub fn new() -> Self {
+        // An array of `MaybeUninit` needs no initialization
+        Self::from_storage(unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() })
+    }
+}
+
+impl<T, const N: usize> Default for RingBufferU<T, [MaybeUninit<T>; N]> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_pop_test() {
+        let mut rb = ArrayRingBuffer::<_, 2>::new();
+        assert_eq!(rb.pop(), None);
+        rb.push(3);
+        assert_eq!(rb.pop(), Some(3));
+        assert_eq!(rb.pop(), None);
+    }
+
+    #[test]
+    fn overwrite_test() {
+        let mut rb = ArrayRingBuffer::<_, 3>::new();
+        for i in 1..8 {
+            rb.push(i);
+        }
+        assert_eq!(rb.push(8), Some(5));
+        assert_eq!(rb.try_push(9), Err(9));
+        assert_eq!(format!("{:?}", rb), "[6, 7, 8]");
+        assert_eq!(rb.into_iter().rev().collect::<Vec<_>>(), vec![8, 7, 6]);
+    }
+
+    #[test]
+    fn drop_test() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut rb = ArrayRingBuffer::<_, 4>::new();
+        rb.extend((0..6).map(|_| Rc::clone(&counter)));
+        assert_eq!(Rc::strong_count(&counter), 5);
+        assert_eq!(rb.iter().count(), 4);
+        drop(rb);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
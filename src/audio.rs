@@ -0,0 +1,135 @@
+use crate::ringbuffer::RingBuffer;
+
+/// Interleaved audio: every frame holds one sample per channel, and frames
+/// only ever enter and leave the buffer whole, so channels cannot drift out
+/// of step. Sizes and counts are in frames unless they say otherwise.
+///
+/// Like `RingBuffer`, pushing to a full buffer overwrites the oldest frames,
+/// which is what a consumer that fell behind usually wants to hear.
+pub struct AudioRingBuffer {
+    samples: RingBuffer<f32>,
+    channels: usize,
+}
+
+impl AudioRingBuffer {
+    /// # Panics
+    ///
+    /// If `channels` is zero.
+    pub fn new(channels: usize, frames: usize) -> Self {
+        assert!(channels != 0, "audio needs at least one channel");
+        AudioRingBuffer {
+            samples: RingBuffer::with_capacity(channels * frames),
+            channels,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.capacity() / self.channels
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.samples.is_full()
+    }
+
+    /// Appends interleaved frames, returning how many frames were dropped
+    /// from the front to make room.
+    ///
+    /// # Panics
+    ///
+    /// If `samples` does not hold a whole number of frames.
+    pub fn push_frames(&mut self, samples: &[f32]) -> usize {
+        assert!(
+            samples.len().is_multiple_of(self.channels),
+            "samples do not make up whole frames"
+        );
+        let frames = samples.len() / self.channels;
+        let free = self.capacity() - self.len();
+        self.samples.extend_from_slice(samples);
+        frames.saturating_sub(free)
+    }
+
+    /// Removes the oldest frame into `frame`, one sample per channel.
+    ///
+    /// # Panics
+    ///
+    /// If `frame` is not exactly one frame long.
+    pub fn pop_frame(&mut self, frame: &mut [f32]) -> bool {
+        assert_eq!(frame.len(), self.channels, "not a single frame");
+        self.pop_frames_into(frame) == 1
+    }
+
+    /// Moves as many whole frames as fit into `out` and returns how many;
+    /// samples past the last whole frame in `out` are left alone.
+    pub fn pop_frames_into(&mut self, out: &mut [f32]) -> usize {
+        let frames = self.len().min(out.len() / self.channels);
+        let n = frames * self.channels;
+        for (slot, &sample) in out[..n].iter_mut().zip(self.samples.iter()) {
+            *slot = sample;
+        }
+        self.samples.truncate_front(self.samples.len() - n);
+        frames
+    }
+
+    /// Samples of the frame `index` places from the front.
+    pub fn frame(&self, index: usize) -> Option<impl Iterator<Item = &f32>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(
+            self.samples
+                .iter()
+                .skip(index * self.channels)
+                .take(self.channels),
+        )
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_test() {
+        let mut audio = AudioRingBuffer::new(2, 3);
+        assert_eq!(audio.capacity(), 3);
+        assert_eq!(audio.push_frames(&[0.0, 0.5, 1.0, 1.5]), 0);
+        assert_eq!(audio.len(), 2);
+        assert_eq!(audio.push_frames(&[2.0, 2.5, 3.0, 3.5]), 1);
+        assert!(audio.is_full());
+        assert_eq!(audio.frame(0).unwrap().collect::<Vec<_>>(), [&1.0, &1.5]);
+
+        // Room for one frame and a half: only the whole frame is taken
+        let mut out = [9.0; 3];
+        assert_eq!(audio.pop_frames_into(&mut out), 1);
+        assert_eq!(out, [1.0, 1.5, 9.0]);
+        let mut frame = [0.0; 2];
+        assert!(audio.pop_frame(&mut frame));
+        assert_eq!(frame, [2.0, 2.5]);
+        let mut out = [0.0; 8];
+        assert_eq!(audio.pop_frames_into(&mut out), 1);
+        assert_eq!(out[..2], [3.0, 3.5]);
+        assert!(!audio.pop_frame(&mut frame));
+    }
+
+    #[test]
+    #[should_panic]
+    fn partial_frame_test() {
+        AudioRingBuffer::new(2, 3).push_frames(&[0.0, 1.0, 2.0]);
+    }
+}
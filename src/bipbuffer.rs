@@ -0,0 +1,186 @@
+//! A bipartite buffer: a byte ring buffer that hands out contiguous regions.
+//!
+//! A plain ring buffer splits data that wraps around the end of its storage,
+//! so a parser or a DMA-style producer would have to copy it together first.
+//! Instead, a `BipBuffer` keeps up to two regions: A, which the reader drains,
+//! and B, which grows from the start of the storage once A reaches the end.
+//! Writes never straddle the end; when B is in use, it becomes A as soon as A
+//! is drained. The price is that a reservation may fail even though enough
+//! bytes are free in total, just not in one piece.
+
+use std::ops::{Deref, DerefMut};
+
+pub struct BipBuffer {
+    buffer: Box<[u8]>,
+    a_start: usize,
+    a_end: usize,
+    b_end: usize, // B always starts at 0, and is empty unless A wrapped
+}
+
+impl BipBuffer {
+    pub fn with_capacity(cap: usize) -> Self {
+        BipBuffer {
+            buffer: vec![0; cap].into_boxed_slice(),
+            a_start: 0,
+            a_end: 0,
+            b_end: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Committed bytes that have not been consumed, in both regions.
+    pub fn len(&self) -> usize {
+        self.a_end - self.a_start + self.b_end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reserves `n` contiguous bytes to be filled in place, or `None` if no
+    /// free region is large enough. Nothing is visible to `read` until the
+    /// reservation is committed; dropping it gives the bytes back.
+    pub fn reserve(&mut self, n: usize) -> Option<Reservation<'_>> {
+        let (start, in_b) = if self.b_end > 0 {
+            (self.b_end, true)
+        } else if self.capacity() - self.a_end >= n {
+            (self.a_end, false)
+        } else {
+            (0, true)
+        };
+        let end = if in_b { self.a_start } else { self.capacity() };
+        if end - start < n {
+            return None;
+        }
+        Some(Reservation {
+            start,
+            len: n,
+            in_b,
+            bip: self,
+        })
+    }
+
+    /// The oldest committed bytes, in one slice. May be shorter than `len`
+    /// when the data continues in region B; consuming all of it brings the
+    /// rest up.
+    pub fn read(&self) -> &[u8] {
+        &self.buffer[self.a_start..self.a_end]
+    }
+
+    /// Discards the first `n` bytes of `read`.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is larger than the slice `read` returns.
+    pub fn consume(&mut self, n: usize) {
+        assert!(
+            n <= self.a_end - self.a_start,
+            "consumed past the readable region"
+        );
+        self.a_start += n;
+        if self.a_start == self.a_end {
+            self.a_start = 0;
+            self.a_end = self.b_end;
+            self.b_end = 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.a_start = 0;
+        self.a_end = 0;
+        self.b_end = 0;
+    }
+}
+
+/// A contiguous writable region handed out by `BipBuffer::reserve`.
+pub struct Reservation<'a> {
+    bip: &'a mut BipBuffer,
+    start: usize,
+    len: usize,
+    in_b: bool,
+}
+
+impl<'a> Reservation<'a> {
+    /// Makes the first `len` bytes of the reservation readable and gives the
+    /// rest back.
+    ///
+    /// # Panics
+    ///
+    /// If `len` is larger than the reservation.
+    pub fn commit(self, len: usize) {
+        assert!(len <= self.len, "committed more than was reserved");
+        if self.in_b {
+            self.bip.b_end += len;
+        } else {
+            self.bip.a_end += len;
+        }
+    }
+}
+
+impl<'a> Deref for Reservation<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bip.buffer[self.start..self.start + self.len]
+    }
+}
+
+impl<'a> DerefMut for Reservation<'a> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bip.buffer[self.start..self.start + self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(bip: &mut BipBuffer, data: &[u8]) -> bool {
+        match bip.reserve(data.len()) {
+            Some(mut region) => {
+                region.copy_from_slice(data);
+                region.commit(data.len());
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn reserve_commit_test() {
+        let mut bip = BipBuffer::with_capacity(8);
+        let mut region = bip.reserve(5).unwrap();
+        region[..3].copy_from_slice(b"abc");
+        region.commit(3);
+        assert_eq!(bip.read(), b"abc");
+        assert!(bip.reserve(6).is_none());
+        bip.reserve(4).unwrap()[0] = b'x'; // Dropped without a commit
+        assert_eq!(bip.len(), 3);
+        assert!(write(&mut bip, b"de"));
+        assert_eq!(bip.read(), b"abcde");
+        bip.consume(5);
+        assert!(bip.is_empty());
+        assert!(write(&mut bip, b"12345678"));
+        assert_eq!(bip.read(), b"12345678");
+    }
+
+    #[test]
+    fn wrap_test() {
+        let mut bip = BipBuffer::with_capacity(8);
+        assert!(write(&mut bip, b"abcdef"));
+        bip.consume(4);
+        // Two bytes are free at the end and four at the start
+        assert!(write(&mut bip, b"123"));
+        assert_eq!(bip.read(), b"ef");
+        assert_eq!(bip.len(), 5);
+        assert!(!write(&mut bip, b"45")); // Would run into region A
+        assert!(write(&mut bip, b"4"));
+        bip.consume(2);
+        assert_eq!(bip.read(), b"1234");
+        assert!(write(&mut bip, b"5678"));
+        assert_eq!(bip.read(), b"12345678");
+    }
+}
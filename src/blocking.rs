@@ -0,0 +1,119 @@
+use crate::ringbuffer::RingBuffer;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// A bounded queue for sharing between threads: a `RingBuffer` behind a
+/// mutex, with condition variables to wait for elements or free space.
+pub struct BlockingRingBuffer<T> {
+    buffer: Mutex<RingBuffer<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl<T> BlockingRingBuffer<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        BlockingRingBuffer {
+            buffer: Mutex::new(RingBuffer::with_capacity(cap)),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RingBuffer<T>> {
+        self.buffer.lock().unwrap()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.lock().is_full()
+    }
+
+    /// Never waits: returns the oldest element if it had to be overwritten.
+    pub fn push(&self, element: T) -> Option<T> {
+        let evicted = self.lock().push(element);
+        self.not_empty.notify_one();
+        evicted
+    }
+
+    pub fn try_push(&self, element: T) -> Result<(), T> {
+        self.lock().try_push(element)?;
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Waits until there is room for the element instead of overwriting.
+    /// With zero capacity there never is, so this blocks forever.
+    pub fn push_blocking(&self, element: T) {
+        let mut buffer = self
+            .not_full
+            .wait_while(self.lock(), |buffer| buffer.is_full())
+            .unwrap();
+        buffer.push(element);
+        drop(buffer);
+        self.not_empty.notify_one();
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let popped = self.lock().pop()?;
+        self.not_full.notify_one();
+        Some(popped)
+    }
+
+    /// Waits until there is an element to pop.
+    pub fn pop_blocking(&self) -> T {
+        let mut buffer = self
+            .not_empty
+            .wait_while(self.lock(), |buffer| buffer.is_empty())
+            .unwrap();
+        let popped = buffer.pop().unwrap();
+        drop(buffer);
+        self.not_full.notify_one();
+        popped
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+        self.not_full.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn push_pop_test() {
+        let buffer = BlockingRingBuffer::with_capacity(2);
+        buffer.push_blocking(1);
+        assert_eq!(buffer.try_push(2), Ok(()));
+        assert_eq!(buffer.try_push(3), Err(3));
+        assert_eq!(buffer.push(3), Some(1));
+        assert_eq!(buffer.pop_blocking(), 2);
+        assert_eq!(buffer.pop(), Some(3));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn threads_test() {
+        let buffer = Arc::new(BlockingRingBuffer::with_capacity(3));
+        let producer = {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || (0..1000).for_each(|i| buffer.push_blocking(i)))
+        };
+        let popped = (0..1000).map(|_| buffer.pop_blocking()).collect::<Vec<_>>();
+        producer.join().unwrap();
+        assert_eq!(popped, (0..1000).collect::<Vec<_>>());
+    }
+}
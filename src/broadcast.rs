@@ -0,0 +1,259 @@
+//! A single-producer broadcast ring buffer.
+//!
+//! The `Sender` overwrites the oldest element once the buffer is full, so a
+//! receiver that falls behind never holds it up. Every `Receiver` keeps its
+//! own read position and sees each element it has not fallen behind on; a
+//! receiver that has been lapped learns how many elements it missed and
+//! resumes from the oldest one still stored.
+//!
+//! Each slot sits behind a `RwLock`, since elements are cloned out rather
+//! than copied. A push therefore waits while a receiver is cloning the
+//! element it is about to overwrite, and a receiver waits while a push
+//! replaces the element it wants; neither waits longer than that.
+
+use crate::sync::{spin_loop, Arc, AtomicU64, Ordering, RwLock};
+use std::error::Error;
+use std::fmt;
+use std::sync::PoisonError;
+
+type Slot<T> = RwLock<Option<(u64, T)>>; // An element with its sequence number
+
+struct Shared<T> {
+    tail: AtomicU64, // Sequence number of the next element to push
+    slots: Box<[Slot<T>]>,
+}
+
+impl<T> Shared<T> {
+    fn slot(&self, seq: u64) -> &Slot<T> {
+        &self.slots[(seq % self.slots.len() as u64) as usize]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// Every element pushed so far has been received.
+    Empty,
+    /// The receiver fell behind and this many elements were overwritten
+    /// before it could read them.
+    Lagged(u64),
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "no new elements"),
+            TryRecvError::Lagged(missed) => write!(f, "receiver lagged by {} elements", missed),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+pub fn with_capacity<T: Clone>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        tail: AtomicU64::new(0),
+        slots: (0..cap).map(|_| RwLock::new(None)).collect(),
+    });
+    let receiver = Receiver {
+        shared: Arc::clone(&shared),
+        next: 0,
+    };
+    (Sender { shared }, receiver)
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone> Sender<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len()
+    }
+
+    /// Returns the oldest element if it had to be overwritten.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        if self.shared.slots.is_empty() {
+            return Some(element);
+        }
+        let seq = self.shared.tail.load(Ordering::Relaxed);
+        let evicted = self
+            .shared
+            .slot(seq)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace((seq, element))
+            .map(|(_, old)| old);
+        self.shared.tail.store(seq + 1, Ordering::Release);
+        evicted
+    }
+
+    /// Creates a receiver that only sees elements pushed from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            next: self.shared.tail.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Cloning a receiver copies its read position.
+#[derive(Clone)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    next: u64, // Sequence number of the next element to receive
+}
+
+impl<T: Clone> Receiver<T> {
+    /// Number of elements this receiver has yet to read, capped at the
+    /// capacity when it has fallen behind.
+    pub fn len(&self) -> usize {
+        let tail = self.shared.tail.load(Ordering::Acquire);
+        ((tail - self.next) as usize).min(self.shared.slots.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            let tail = self.shared.tail.load(Ordering::Acquire);
+            if self.next == tail {
+                return Err(TryRecvError::Empty);
+            }
+            let oldest = tail.saturating_sub(self.shared.slots.len() as u64);
+            if self.next < oldest {
+                let missed = oldest - self.next;
+                self.next = oldest;
+                return Err(TryRecvError::Lagged(missed));
+            }
+            // A slot is never left half-written, so a panic elsewhere while
+            // it was locked does not matter
+            let slot = self
+                .shared
+                .slot(self.next)
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some((seq, element)) = &*slot {
+                if *seq == self.next {
+                    self.next += 1;
+                    return Ok(element.clone());
+                }
+            }
+            // Overwritten after `tail` was loaded, so we are lagging now
+            drop(slot);
+            spin_loop();
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn broadcast_test() {
+        let (mut sender, mut first) = with_capacity(3);
+        sender.push(1);
+        let mut second = sender.subscribe();
+        sender.push(2);
+        assert_eq!(first.try_recv(), Ok(1));
+        assert_eq!(second.try_recv(), Ok(2));
+        assert_eq!(second.try_recv(), Err(TryRecvError::Empty));
+        for i in 3..8 {
+            sender.push(i);
+        }
+        assert_eq!(first.len(), 3);
+        assert_eq!(first.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(first.try_recv(), Ok(5));
+        let mut third = first.clone();
+        assert_eq!(first.try_recv(), Ok(6));
+        assert_eq!(third.try_recv(), Ok(6));
+        assert_eq!(second.try_recv(), Err(TryRecvError::Lagged(2)));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Fragile(u32);
+
+    impl Clone for Fragile {
+        fn clone(&self) -> Self {
+            assert!(self.0 != 1, "cannot clone");
+            Fragile(self.0)
+        }
+    }
+
+    #[test]
+    fn panicking_receiver_test() {
+        let (mut sender, mut receiver) = with_capacity(1);
+        sender.push(Fragile(1));
+        let mut other = receiver.clone();
+        thread::spawn(move || receiver.try_recv())
+            .join()
+            .unwrap_err();
+        assert_eq!(sender.push(Fragile(2)), Some(Fragile(1)));
+        assert_eq!(other.try_recv(), Err(TryRecvError::Lagged(1)));
+        assert_eq!(other.try_recv(), Ok(Fragile(2)));
+    }
+
+    #[test]
+    fn threads_test() {
+        let (mut sender, receiver) = with_capacity(4);
+        let readers = (0..3)
+            .map(|_| {
+                let mut receiver = receiver.clone();
+                thread::spawn(move || {
+                    let mut last = None;
+                    let mut received = 0;
+                    while last != Some(999) {
+                        match receiver.try_recv() {
+                            Ok(x) => {
+                                assert!(last < Some(x));
+                                last = Some(x);
+                                received += 1;
+                            }
+                            Err(TryRecvError::Lagged(_)) => {}
+                            Err(TryRecvError::Empty) => thread::yield_now(),
+                        }
+                    }
+                    received
+                })
+            })
+            .collect::<Vec<_>>();
+        for i in 0..1000 {
+            sender.push(i);
+        }
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn lagging_test() {
+        loom::model(|| {
+            let (mut sender, mut receiver) = with_capacity(1);
+            let handle = thread::spawn(move || {
+                let mut last = None;
+                for _ in 0..2 {
+                    match receiver.try_recv() {
+                        Ok(x) => {
+                            assert!(last < Some(x));
+                            last = Some(x);
+                        }
+                        Err(TryRecvError::Lagged(missed)) => assert_eq!(missed, 1),
+                        Err(TryRecvError::Empty) => {}
+                    }
+                }
+            });
+            sender.push(0);
+            sender.push(1);
+            handle.join().unwrap();
+        });
+    }
+}
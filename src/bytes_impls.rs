@@ -0,0 +1,81 @@
+//! `bytes::Buf` and `BufMut` for byte ring buffers, so codecs can read and
+//! write them in place.
+//!
+//! Unlike `io::Write`, `BufMut` never overwrites: `remaining_mut` is the free
+//! space, and writing past it panics as the trait requires.
+
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+use bytes::buf::UninitSlice;
+use bytes::{Buf, BufMut};
+use std::io::IoSlice;
+
+impl<S: Storage<u8>> Buf for RingBufferU<u8, S> {
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.as_slices().0
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let (front, back) = self.as_slices();
+        let mut n = 0;
+        for (slot, run) in dst.iter_mut().zip([front, back].iter()) {
+            if run.is_empty() {
+                break;
+            }
+            *slot = IoSlice::new(run);
+            n += 1;
+        }
+        n
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.len(),
+            "cannot advance past the end of the buffer"
+        );
+        self.truncate_front(self.len() - cnt);
+    }
+}
+
+unsafe impl<S: Storage<u8>> BufMut for RingBufferU<u8, S> {
+    fn remaining_mut(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.remaining_mut(),
+            "cannot advance past the free space"
+        );
+        self.assume_pushed(cnt);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::uninit(self.spare_slices_mut().0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn buf_test() {
+        let mut rb = RingBuffer::with_capacity(6);
+        rb.put_slice(b"abcd");
+        assert_eq!(rb.get_u16(), u16::from_be_bytes(*b"ab"));
+        rb.put_u32(u32::from_be_bytes(*b"efgh")); // Wraps around
+        assert_eq!(rb.remaining_mut(), 0);
+        assert_eq!(rb.chunk(), b"cdef");
+        let mut slices = [IoSlice::new(&[]); 2];
+        assert_eq!(rb.chunks_vectored(&mut slices), 2);
+        assert_eq!(&*slices[1], b"gh");
+        assert_eq!(rb.copy_to_bytes(6).as_ref(), b"cdefgh");
+        assert!(!rb.has_remaining());
+    }
+}
@@ -0,0 +1,299 @@
+//! A bounded multi-producer single-consumer async channel.
+//!
+//! Elements are stored in a `RingBuffer` behind a mutex, so it works with any
+//! executor. `send` waits while the channel is full and `recv` while it is
+//! empty; the receiver sees `None` once every sender is dropped and the
+//! remaining elements have been received.
+
+use crate::ringbuffer::RingBuffer;
+use std::error::Error;
+use std::fmt;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+struct State<T> {
+    buffer: RingBuffer<T>,
+    senders: usize,
+    closed: bool, // The receiver closed the channel or was dropped
+    receiver: Option<Waker>,
+    blocked_senders: Vec<Waker>,
+}
+
+impl<T> State<T> {
+    fn wake_receiver(&mut self) {
+        if let Some(waker) = self.receiver.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_senders(&mut self) {
+        self.blocked_senders.drain(..).for_each(Waker::wake);
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Creates a bounded channel that holds up to `cap` elements, with any
+/// number of senders and one receiver.
+pub fn channel<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(cap != 0, "channel capacity must be non-zero");
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: RingBuffer::with_capacity(cap),
+            senders: 1,
+            closed: false,
+            receiver: None,
+            blocked_senders: Vec::new(),
+        }),
+    });
+    let sender = Sender {
+        shared: Arc::clone(&shared),
+    };
+    (sender, Receiver { shared })
+}
+
+/// The receiver is gone or closed the channel; the element is handed back.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SendError(..)")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("Full(..)"),
+            TrySendError::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "channel is full"),
+            TrySendError::Closed(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    /// Every sender is gone and the channel has been drained.
+    Closed,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "channel is empty"),
+            TryRecvError::Closed => write!(f, "channel is empty and closed"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    pub fn try_send(&self, element: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(TrySendError::Closed(element));
+        }
+        state.buffer.try_push(element).map_err(TrySendError::Full)?;
+        state.wake_receiver();
+        Ok(())
+    }
+
+    /// Waits for room in the channel, then sends the element.
+    pub async fn send(&self, element: T) -> Result<(), SendError<T>> {
+        let mut pending = Some(element);
+        poll_fn(|cx| {
+            let mut state = self.shared.lock();
+            let element = pending.take().expect("polled after completion");
+            if state.closed {
+                return Poll::Ready(Err(SendError(element)));
+            }
+            match state.buffer.try_push(element) {
+                Ok(()) => {
+                    state.wake_receiver();
+                    Poll::Ready(Ok(()))
+                }
+                Err(element) => {
+                    pending = Some(element);
+                    if !state
+                        .blocked_senders
+                        .iter()
+                        .any(|w| w.will_wake(cx.waker()))
+                    {
+                        state.blocked_senders.push(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().closed
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            state.wake_receiver();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.shared.lock();
+        match state.buffer.pop() {
+            Some(element) => {
+                state.wake_senders();
+                Ok(element)
+            }
+            None if state.senders == 0 || state.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Waits for an element; `None` once every sender is gone (or the
+    /// channel was closed) and nothing is left to receive.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| {
+            let mut state = self.shared.lock();
+            if let Some(element) = state.buffer.pop() {
+                state.wake_senders();
+                return Poll::Ready(Some(element));
+            }
+            if state.senders == 0 || state.closed {
+                return Poll::Ready(None);
+            }
+            state.receiver = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Stops further sends; elements already in the channel can still be
+    /// received.
+    pub fn close(&mut self) {
+        let mut state = self.shared.lock();
+        state.closed = true;
+        state.wake_senders();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn send_recv_test() {
+        let (sender, mut receiver) = channel(2);
+        let other = sender.clone();
+        assert_eq!(sender.try_send(1), Ok(()));
+        assert_eq!(other.try_send(2), Ok(()));
+        assert_eq!(other.try_send(3), Err(TrySendError::Full(3)));
+        assert_eq!(block_on(receiver.recv()), Some(1));
+        drop(sender);
+        drop(other);
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(block_on(receiver.recv()), None);
+
+        let (sender, mut receiver) = channel(1);
+        sender.try_send(1).unwrap();
+        receiver.close();
+        assert_eq!(block_on(sender.send(2)), Err(SendError(2)));
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn threads_test() {
+        let (sender, mut receiver) = channel(3);
+        let handles = (0..4)
+            .map(|t| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    block_on(async {
+                        for i in 0..250 {
+                            sender.send(t * 250 + i).await.unwrap();
+                        }
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+        let mut received = block_on(async {
+            let mut received = Vec::new();
+            while let Some(x) = receiver.recv().await {
+                received.push(x);
+            }
+            received
+        });
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        received.sort_unstable();
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt;
+
+/// A logical position in a ring buffer that stays put while elements are
+/// pushed to the back and popped from the front.
+///
+/// Cursors count every element that ever entered the buffer, so they keep
+/// pointing at the same element until it leaves the buffer. Reordering
+/// operations (`insert`, `remove`, `rotate_*`, `retain`, ...) do not track
+/// elements; after them a cursor refers to whatever occupies its position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    seq: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorError {
+    /// The element was popped or overwritten.
+    Overwritten,
+    /// The element has not been pushed yet.
+    Pending,
+}
+
+impl Cursor {
+    pub(crate) fn new(seq: usize) -> Self {
+        Cursor { seq }
+    }
+
+    pub fn move_next(&mut self) {
+        self.seq = self.seq.wrapping_add(1);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.seq = self.seq.wrapping_sub(1);
+    }
+
+    /// Resolves the cursor to a logical index given the sequence number of
+    /// the front element and the current length.
+    pub(crate) fn index(self, front: usize, len: usize) -> Result<usize, CursorError> {
+        let offset = self.seq.wrapping_sub(front);
+        if offset < len {
+            Ok(offset)
+        } else if (offset as isize) < 0 {
+            Err(CursorError::Overwritten)
+        } else {
+            Err(CursorError::Pending)
+        }
+    }
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CursorError::Overwritten => write!(f, "element has left the buffer"),
+            CursorError::Pending => write!(f, "element has not been pushed yet"),
+        }
+    }
+}
+
+impl Error for CursorError {}
@@ -0,0 +1,173 @@
+use crate::ringbuffer::{RBRefIter, RingBuffer};
+
+/// Receives the elements an `EvictingRingBuffer` pushes out to make room.
+///
+/// Implemented for every `FnMut(T)` closure; implement it directly for a
+/// sink that needs more than a closure, such as one that batches writes.
+pub trait OnEvict<T> {
+    fn evict(&mut self, element: T);
+}
+
+impl<T, F: FnMut(T)> OnEvict<T> for F {
+    fn evict(&mut self, element: T) {
+        self(element)
+    }
+}
+
+/// A ring buffer that hands every overwritten element to a hook instead of
+/// dropping it, e.g. to flush old records to disk.
+///
+/// The hook sees elements in the order they leave: oldest first, and only
+/// those pushed out for lack of room. Elements that are popped, cleared or
+/// still in the buffer when it is dropped never reach it.
+pub struct EvictingRingBuffer<T, E: OnEvict<T>> {
+    buffer: RingBuffer<T>,
+    on_evict: E,
+}
+
+impl<T, E: OnEvict<T>> EvictingRingBuffer<T, E> {
+    pub fn with_capacity(cap: usize, on_evict: E) -> Self {
+        EvictingRingBuffer {
+            buffer: RingBuffer::with_capacity(cap),
+            on_evict,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    /// With zero capacity, `element` itself goes straight to the hook.
+    pub fn push(&mut self, element: T) {
+        if let Some(evicted) = self.buffer.push(element) {
+            self.on_evict.evict(evicted);
+        }
+    }
+
+    /// Inserts at `index`, evicting the oldest element when full; see
+    /// `RingBufferU::insert`.
+    pub fn insert(&mut self, index: usize, element: T) {
+        if let Some(evicted) = self.buffer.insert(index, element) {
+            self.on_evict.evict(evicted);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.buffer.pop()
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.buffer.pop_back()
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.buffer.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.buffer.back()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.buffer.get(index)
+    }
+
+    pub fn iter(&self) -> RBRefIter<'_, T> {
+        self.buffer.iter()
+    }
+
+    /// Reallocates to hold `cap` elements. When shrinking, the oldest
+    /// elements are evicted.
+    pub fn set_capacity(&mut self, cap: usize) {
+        while self.buffer.len() > cap {
+            let oldest = self.buffer.pop().unwrap();
+            self.on_evict.evict(oldest);
+        }
+        self.buffer.set_capacity(cap);
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    pub fn on_evict(&self) -> &E {
+        &self.on_evict
+    }
+
+    pub fn on_evict_mut(&mut self) -> &mut E {
+        &mut self.on_evict
+    }
+
+    pub fn into_parts(self) -> (RingBuffer<T>, E) {
+        (self.buffer, self.on_evict)
+    }
+}
+
+impl<T, E: OnEvict<T>> Extend<T> for EvictingRingBuffer<T, E> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closure_test() {
+        let mut evicted = Vec::new();
+        let mut rb = EvictingRingBuffer::with_capacity(2, |x| evicted.push(x));
+        rb.extend(1..=4);
+        assert_eq!(rb.pop(), Some(3));
+        rb.insert(0, 5);
+        rb.push(6);
+        rb.set_capacity(1);
+        rb.clear();
+        drop(rb);
+        assert_eq!(evicted, [1, 2, 5, 4]);
+    }
+
+    struct Batches {
+        pending: Vec<String>,
+        flushed: Vec<Vec<String>>,
+    }
+
+    impl OnEvict<String> for Batches {
+        fn evict(&mut self, element: String) {
+            self.pending.push(element);
+            if self.pending.len() == 2 {
+                self.flushed.push(std::mem::take(&mut self.pending));
+            }
+        }
+    }
+
+    #[test]
+    fn trait_test() {
+        let sink = Batches {
+            pending: Vec::new(),
+            flushed: Vec::new(),
+        };
+        let mut rb = EvictingRingBuffer::with_capacity(1, sink);
+        for word in ["a", "b", "c", "d"] {
+            rb.push(word.to_string());
+        }
+        assert_eq!(rb.on_evict().flushed, [["a", "b"]]);
+        let (buffer, sink) = rb.into_parts();
+        assert_eq!(buffer.iter().collect::<Vec<_>>(), ["d"]);
+        assert_eq!(sink.pending, ["c"]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Length-delimited frames on byte ring buffers.
+//!
+//! Each frame is a big-endian `u32` payload length followed by the payload.
+//! Unlike plain writes, pushing a frame never overwrites: dropping the oldest
+//! bytes would cut a frame in half and lose the framing for good.
+
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+const PREFIX: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// Not enough free space for the frame right now.
+    Full,
+    /// The payload does not fit in the length prefix, or the frame would not
+    /// fit in the buffer even when empty.
+    TooLong,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Full => write!(f, "not enough room for the frame"),
+            FrameError::TooLong => write!(f, "frame is larger than the buffer"),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+impl<S: Storage<u8>> RingBufferU<u8, S> {
+    /// Appends a length prefix and `payload`, or nothing at all if the frame
+    /// does not fit.
+    pub fn push_frame(&mut self, payload: &[u8]) -> Result<(), FrameError> {
+        let len = u32::try_from(payload.len()).map_err(|_| FrameError::TooLong)?;
+        let frame = PREFIX + payload.len();
+        if frame > self.capacity() {
+            return Err(FrameError::TooLong);
+        }
+        if frame > self.capacity() - self.len() {
+            return Err(FrameError::Full);
+        }
+        self.extend_from_slice(&len.to_be_bytes());
+        self.extend_from_slice(payload);
+        Ok(())
+    }
+
+    /// Payload length of the frame at the front, once its prefix is buffered.
+    pub fn peek_frame_len(&self) -> Option<usize> {
+        if self.len() < PREFIX {
+            return None;
+        }
+        let mut prefix = [0; PREFIX];
+        for (byte, &x) in prefix.iter_mut().zip(self.iter()) {
+            *byte = x;
+        }
+        Some(u32::from_be_bytes(prefix) as usize)
+    }
+
+    /// Removes and returns the payload of the frame at the front, or `None`
+    /// while only part of it is buffered.
+    pub fn pop_frame(&mut self) -> Option<Vec<u8>> {
+        let len = self.peek_frame_len()?;
+        if self.len() - PREFIX < len {
+            return None;
+        }
+        let payload = self.iter().skip(PREFIX).take(len).copied().collect();
+        self.truncate_front(self.len() - PREFIX - len);
+        Some(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn frame_test() {
+        let mut rb = RingBuffer::with_capacity(16);
+        assert_eq!(rb.push_frame(b"hello"), Ok(()));
+        assert_eq!(rb.push_frame(b"abc"), Ok(()));
+        assert_eq!(rb.push_frame(b""), Err(FrameError::Full));
+        assert_eq!(rb.push_frame(&[0; 13]), Err(FrameError::TooLong));
+        assert_eq!(rb.len(), 16);
+        assert_eq!(rb.pop_frame(), Some(b"hello".to_vec()));
+        // The next frame wraps around the end of the storage
+        assert_eq!(rb.push_frame(b"wrap"), Ok(()));
+        assert_eq!(rb.pop_frame(), Some(b"abc".to_vec()));
+        assert_eq!(rb.pop_frame(), Some(b"wrap".to_vec()));
+        assert_eq!(rb.pop_frame(), None);
+    }
+
+    #[test]
+    fn partial_frame_test() {
+        let mut rb = RingBuffer::with_capacity(16);
+        rb.extend_from_slice(&[0, 0]);
+        assert_eq!(rb.pop_frame(), None);
+        rb.extend_from_slice(&[0, 3, b'x']);
+        assert_eq!(rb.peek_frame_len(), Some(3));
+        assert_eq!(rb.pop_frame(), None);
+        rb.extend_from_slice(b"yz");
+        assert_eq!(rb.pop_frame(), Some(b"xyz".to_vec()));
+        assert!(rb.is_empty());
+    }
+}
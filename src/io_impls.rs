@@ -0,0 +1,74 @@
+//! Byte ring buffers as bounded staging buffers for `std::io`.
+//!
+//! Writing follows the usual overwrite semantics: the whole input is always
+//! accepted and, once the buffer is full, the oldest bytes make room for it.
+//! Check `capacity() - len()` before writing when nothing may be lost.
+//! Reading consumes from the front.
+
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+use std::io::{self, BufRead, Read, Write};
+use std::mem::MaybeUninit;
+
+impl<S: Storage<u8>> Write for RingBufferU<u8, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: Storage<u8>> Read for RingBufferU<u8, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // An initialized byte is a valid `MaybeUninit<u8>`
+        let dst = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        Ok(self.pop_into_uninit(dst))
+    }
+}
+
+/// `fill_buf` returns the contiguous run at the front, which may be shorter
+/// than `len()` when the contents wrap around.
+impl<S: Storage<u8>> BufRead for RingBufferU<u8, S> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.as_slices().0)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.truncate_front(self.len().saturating_sub(amt));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ringbuffer::RingBuffer;
+    use std::io::{BufRead, Read, Write};
+
+    #[test]
+    fn read_write_test() {
+        let mut rb = RingBuffer::with_capacity(8);
+        rb.write_all(b"hello, ").unwrap();
+        let mut buf = [0; 3];
+        assert_eq!(rb.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"hel");
+        write!(rb, "world!").unwrap(); // Overwrites "lo"
+        let mut rest = String::new();
+        rb.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, ", world!");
+        assert_eq!(rb.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn buf_read_test() {
+        let mut rb = RingBuffer::with_capacity(6);
+        rb.write_all(b"ab\ncd").unwrap();
+        rb.consume(3);
+        rb.write_all(b"e\nf").unwrap(); // Wraps around the end of storage
+        let mut line = String::new();
+        assert_eq!(rb.read_line(&mut line).unwrap(), 4);
+        assert_eq!(line, "cde\n");
+        assert_eq!(rb.fill_buf().unwrap(), b"f");
+    }
+}
@@ -1,4 +1,51 @@
 #![allow(dead_code)]
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+#[cfg(feature = "rkyv")]
+pub mod archive;
+pub mod arrayringbuffer;
+pub mod audio;
+pub mod bipbuffer;
+pub mod blocking;
+pub mod broadcast;
+#[cfg(feature = "bytes")]
+mod bytes_impls;
+pub mod channel;
+pub mod cursor;
+pub mod evicting;
+pub mod framing;
+mod io_impls;
+#[cfg(all(feature = "mirror", target_os = "linux"))]
+pub mod mirror;
+#[cfg(test)]
+mod model_tests;
+pub mod monotonic;
+pub mod mpmc;
+mod notify;
+pub mod padded;
+pub mod persistent;
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impls;
+pub mod realtime;
+pub mod reservoir;
 pub mod ringbuffer;
 pub mod ringbufferu;
+pub mod seqlock;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "shm")]
+pub mod shm;
+pub mod sliceringbuffer;
+pub mod snapshot;
+pub mod spsc;
+pub mod stats;
+pub mod storage;
+mod sync;
+pub mod timed;
+pub mod transaction;
+pub mod triplebuffer;
+pub mod wait;
+pub mod watermark;
+
+pub use channel::channel;
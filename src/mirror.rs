@@ -0,0 +1,217 @@
+//! A byte ring buffer whose contents are always one contiguous slice.
+//!
+//! The same physical pages are mapped twice, back to back, so reading or
+//! writing past the end of the first mapping lands at the start of the
+//! buffer again. Whatever the position of the data, `as_slice` and
+//! `spare_mut` can then hand it out without splitting it at the wrap point,
+//! which suits network and audio I/O that wants one slice per call.
+//!
+//! Only available on Linux, where the pages come from a `memfd`. The capacity
+//! is rounded up to a whole number of pages.
+
+use std::io;
+use std::ptr;
+use std::slice;
+
+pub struct MirroredBuffer {
+    ptr: *mut u8, // Start of the first of two mappings of `cap` bytes each
+    cap: usize,
+    head: usize, // Offset of the oldest byte, always below `cap`
+    len: usize,
+}
+
+// The mappings are owned exclusively, like a `Box<[u8]>`.
+unsafe impl Send for MirroredBuffer {}
+unsafe impl Sync for MirroredBuffer {}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+impl MirroredBuffer {
+    /// Maps a buffer of at least `min_cap` bytes.
+    pub fn with_capacity(min_cap: usize) -> io::Result<Self> {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let cap = min_cap.max(1).div_ceil(page) * page;
+        let name = b"circus-mirror\0";
+        let fd = check(unsafe { libc::memfd_create(name.as_ptr().cast(), libc::MFD_CLOEXEC) })?;
+        let mapped = unsafe { Self::map_twice(fd, cap) };
+        unsafe { libc::close(fd) };
+        Ok(MirroredBuffer {
+            ptr: mapped?,
+            cap,
+            head: 0,
+            len: 0,
+        })
+    }
+
+    // Reserves twice the address space first, so that nothing else can be
+    // mapped between the two halves, then maps the file over both halves.
+    unsafe fn map_twice(fd: libc::c_int, cap: usize) -> io::Result<*mut u8> {
+        check(libc::ftruncate(fd, cap as libc::off_t))?;
+        let base = libc::mmap(
+            ptr::null_mut(),
+            2 * cap,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        for half in 0..2 {
+            let addr = libc::mmap(
+                base.cast::<u8>().add(half * cap).cast(),
+                cap,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_FIXED,
+                fd,
+                0,
+            );
+            if addr == libc::MAP_FAILED {
+                let err = io::Error::last_os_error();
+                libc::munmap(base, 2 * cap);
+                return Err(err);
+            }
+        }
+        Ok(base.cast())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.cap
+    }
+
+    /// All buffered bytes, oldest first.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.add(self.head), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.add(self.head), self.len) }
+    }
+
+    /// All free space, right after the buffered bytes. Bytes written here
+    /// become part of the contents once they are committed.
+    pub fn spare_mut(&mut self) -> &mut [u8] {
+        let tail = (self.head + self.len) % self.cap;
+        unsafe { slice::from_raw_parts_mut(self.ptr.add(tail), self.cap - self.len) }
+    }
+
+    /// Appends the first `n` bytes of `spare_mut` to the contents.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is larger than the free space.
+    pub fn commit(&mut self, n: usize) {
+        assert!(
+            n <= self.cap - self.len,
+            "committed more than the free space"
+        );
+        self.len += n;
+    }
+
+    /// Drops the oldest `n` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is larger than `len`.
+    pub fn consume(&mut self, n: usize) {
+        assert!(n <= self.len, "consumed more than was buffered");
+        self.head = (self.head + n) % self.cap;
+        self.len -= n;
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+impl Drop for MirroredBuffer {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.cast(), 2 * self.cap) };
+    }
+}
+
+/// Copies as much as fits; a full buffer writes nothing.
+impl io::Write for MirroredBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let spare = self.spare_mut();
+        let n = spare.len().min(buf.len());
+        spare[..n].copy_from_slice(&buf[..n]);
+        self.commit(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Read for MirroredBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.len.min(buf.len());
+        buf[..n].copy_from_slice(&self.as_slice()[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl io::BufRead for MirroredBuffer {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.as_slice())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        MirroredBuffer::consume(self, amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn wrap_test() {
+        let mut buffer = MirroredBuffer::with_capacity(100).unwrap();
+        let cap = buffer.capacity();
+        assert!(cap >= 100);
+        let data = (0..cap).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        assert_eq!(buffer.write(&data[..cap - 10]).unwrap(), cap - 10);
+        buffer.consume(cap - 20);
+        // These bytes go past the end of the first mapping
+        assert_eq!(buffer.write(&data[..30]).unwrap(), 30);
+        assert_eq!(buffer.len(), 40);
+        let contents = buffer.as_slice();
+        assert_eq!(&contents[..10], &data[cap - 20..cap - 10]);
+        assert_eq!(&contents[10..], &data[..30]);
+
+        let mut out = [0; 15];
+        buffer.read_exact(&mut out).unwrap();
+        assert_eq!(&out[..10], &data[cap - 20..cap - 10]);
+        assert_eq!(&out[10..], &data[..5]);
+        buffer.spare_mut().fill(7);
+        buffer.commit(cap - 25);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.write(&[1]).unwrap(), 0);
+        assert!(buffer.as_slice()[25..].iter().all(|&b| b == 7));
+    }
+}
@@ -0,0 +1,185 @@
+//! Differential tests: every ring buffer variant is driven with the same
+//! random operations as a `VecDeque`-based model of the intended semantics,
+//! and compared with it after every step. A new variant only needs a line in
+//! `variants_test`.
+
+use crate::arrayringbuffer::ArrayRingBuffer;
+use crate::ringbuffer::RingBuffer;
+use crate::ringbufferu::RingBufferU;
+use crate::sliceringbuffer::SliceRingBuffer;
+use crate::storage::Storage;
+use std::collections::VecDeque;
+use std::mem::MaybeUninit;
+
+const STEPS: usize = 2000;
+
+/// The reference semantics: a full buffer makes room by dropping from the
+/// opposite end of the one being written to.
+struct Model {
+    deque: VecDeque<u32>,
+    cap: usize,
+}
+
+impl Model {
+    fn is_full(&self) -> bool {
+        self.deque.len() == self.cap
+    }
+
+    fn push(&mut self, x: u32) -> Option<u32> {
+        if self.cap == 0 {
+            return Some(x);
+        }
+        let evicted = if self.is_full() {
+            self.deque.pop_front()
+        } else {
+            None
+        };
+        self.deque.push_back(x);
+        evicted
+    }
+
+    fn push_front(&mut self, x: u32) -> Option<u32> {
+        if self.cap == 0 {
+            return Some(x);
+        }
+        let evicted = if self.is_full() {
+            self.deque.pop_back()
+        } else {
+            None
+        };
+        self.deque.push_front(x);
+        evicted
+    }
+
+    fn insert(&mut self, mut index: usize, x: u32) -> Option<u32> {
+        let evicted = if self.is_full() {
+            if index == 0 {
+                return Some(x);
+            }
+            index -= 1;
+            self.deque.pop_front()
+        } else {
+            None
+        };
+        self.deque.insert(index, x);
+        evicted
+    }
+}
+
+/// xorshift64*, so failures reproduce from the seed alone.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+}
+
+fn run<S: Storage<u32>>(mut rb: RingBufferU<u32, S>, seed: u64) {
+    let mut model = Model {
+        deque: VecDeque::new(),
+        cap: rb.capacity(),
+    };
+    let mut rng = Rng(seed);
+    for step in 0..STEPS {
+        let x = rng.next() as u32 % 100;
+        let len = model.deque.len();
+        let op = rng.below(16);
+        let context = format!("seed {} step {} op {}", seed, step, op);
+        match op {
+            0..=2 => assert_eq!(rb.push(x), model.push(x), "{}", context),
+            3 => assert_eq!(rb.push_front(x), model.push_front(x), "{}", context),
+            4 => {
+                let expected = if model.is_full() {
+                    Err(x)
+                } else {
+                    model.deque.push_back(x);
+                    Ok(())
+                };
+                assert_eq!(rb.try_push(x), expected, "{}", context);
+            }
+            5 => assert_eq!(rb.pop(), model.deque.pop_front(), "{}", context),
+            6 => assert_eq!(rb.pop_back(), model.deque.pop_back(), "{}", context),
+            7 => {
+                let index = rng.below(len + 1);
+                assert_eq!(rb.insert(index, x), model.insert(index, x), "{}", context);
+            }
+            8 => {
+                let index = rng.below(len + 1);
+                assert_eq!(rb.remove(index), model.deque.remove(index), "{}", context);
+            }
+            9 => {
+                let index = rng.below(len + 1);
+                let expected = model.deque.swap_remove_front(index);
+                assert_eq!(rb.swap_remove_front(index), expected, "{}", context);
+            }
+            10 => {
+                let index = rng.below(len + 1);
+                let expected = model.deque.swap_remove_back(index);
+                assert_eq!(rb.swap_remove_back(index), expected, "{}", context);
+            }
+            11 => {
+                let n = rng.below(len + 1);
+                rb.rotate_left(n);
+                model.deque.rotate_left(n);
+            }
+            12 => {
+                let n = rng.below(len + 1);
+                rb.truncate_front(n);
+                while model.deque.len() > n {
+                    model.deque.pop_front();
+                }
+            }
+            13 => {
+                let src = (0..rng.below(model.cap + 3))
+                    .map(|_| rng.next() as u32 % 100)
+                    .collect::<Vec<_>>();
+                rb.extend_from_slice(&src);
+                for &x in &src {
+                    model.push(x);
+                }
+            }
+            14 => {
+                rb.retain(|&x| x % 3 != 0);
+                model.deque.retain(|&x| x % 3 != 0);
+            }
+            _ => {
+                if rng.below(8) == 0 {
+                    rb.clear();
+                    model.deque.clear();
+                }
+            }
+        }
+        assert_eq!(rb.len(), model.deque.len(), "{}", context);
+        assert_eq!(rb.is_full(), model.is_full(), "{}", context);
+        assert!(rb.iter().eq(model.deque.iter()), "{}", context);
+        let (front, back) = rb.as_slices();
+        assert!(
+            front.iter().chain(back).eq(model.deque.iter()),
+            "{}",
+            context
+        );
+        let index = rng.below(len + 1);
+        assert_eq!(rb.get(index), model.deque.get(index), "{}", context);
+    }
+}
+
+#[test]
+fn variants_test() {
+    for seed in 1..=8 {
+        for cap in 0..=6 {
+            run(RingBuffer::with_capacity(cap), seed);
+        }
+        run(RingBufferU::with_capacity_pow2(8), seed);
+        run(ArrayRingBuffer::<u32, 5>::new(), seed);
+        let mut slots = [MaybeUninit::uninit(); 7];
+        run(SliceRingBuffer::from_slice_storage(&mut slots), seed);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::cursor::Cursor;
+use crate::ringbuffer::{RBRefIter, RingBuffer};
+use std::collections::VecDeque;
+
+/// A ring buffer that also tracks the minimum and maximum of its window.
+///
+/// Alongside the data it keeps two monotonic queues of cursors: candidates for
+/// the minimum in increasing order and candidates for the maximum in
+/// decreasing order, so `min()` and `max()` are O(1) and each push or pop is
+/// amortized O(1).
+pub struct MonotonicRingBuffer<T> {
+    buffer: RingBuffer<T>,
+    mins: VecDeque<Cursor>,
+    maxs: VecDeque<Cursor>,
+}
+
+impl<T: PartialOrd> MonotonicRingBuffer<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        MonotonicRingBuffer {
+            buffer: RingBuffer::with_capacity(cap),
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    /// Returns the oldest element if it had to be overwritten.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        if self.capacity() == 0 {
+            return Some(element);
+        }
+        let evicted = self.buffer.push(element);
+        self.forget_evicted();
+        let mut newest = self.buffer.cursor_end();
+        newest.move_prev();
+        let buffer = &self.buffer;
+        let value = |cursor| buffer.cursor_get(cursor).unwrap();
+        while self
+            .mins
+            .back()
+            .is_some_and(|&last| value(last) > value(newest))
+        {
+            self.mins.pop_back();
+        }
+        while self
+            .maxs
+            .back()
+            .is_some_and(|&last| value(last) < value(newest))
+        {
+            self.maxs.pop_back();
+        }
+        self.mins.push_back(newest);
+        self.maxs.push_back(newest);
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let popped = self.buffer.pop();
+        self.forget_evicted();
+        popped
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.mins.clear();
+        self.maxs.clear();
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        self.mins.front().map(|&cursor| self.value(cursor))
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        self.maxs.front().map(|&cursor| self.value(cursor))
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.buffer.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.buffer.back()
+    }
+
+    pub fn iter(&self) -> RBRefIter<'_, T> {
+        self.buffer.iter()
+    }
+
+    /// Read-only access to the window; mutating it would invalidate the queues.
+    pub fn buffer(&self) -> &RingBuffer<T> {
+        &self.buffer
+    }
+
+    pub fn into_inner(self) -> RingBuffer<T> {
+        self.buffer
+    }
+
+    fn value(&self, cursor: Cursor) -> &T {
+        self.buffer.cursor_get(cursor).unwrap()
+    }
+
+    fn forget_evicted(&mut self) {
+        let buffer = &self.buffer;
+        for queue in [&mut self.mins, &mut self.maxs].iter_mut() {
+            while let Some(&first) = queue.front() {
+                if buffer.cursor_index(first).is_ok() {
+                    break;
+                }
+                queue.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_min_max_test() {
+        let mut rb = MonotonicRingBuffer::with_capacity(3);
+        assert_eq!(rb.min(), None);
+        let mut extremes = Vec::new();
+        for &x in &[5, 3, 4, 1, 6, 6, 2, 7] {
+            rb.push(x);
+            extremes.push((*rb.min().unwrap(), *rb.max().unwrap()));
+        }
+        assert_eq!(
+            extremes,
+            vec![
+                (5, 5),
+                (3, 5),
+                (3, 5),
+                (1, 4),
+                (1, 6),
+                (1, 6),
+                (2, 6),
+                (2, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn pop_test() {
+        let mut rb = MonotonicRingBuffer::with_capacity(4);
+        for &x in &[1, 5, 2, 4] {
+            rb.push(x);
+        }
+        assert_eq!(rb.pop(), Some(1));
+        assert_eq!((rb.min(), rb.max()), (Some(&2), Some(&5)));
+        assert_eq!(rb.pop(), Some(5));
+        assert_eq!((rb.min(), rb.max()), (Some(&2), Some(&4)));
+        rb.clear();
+        assert_eq!((rb.min(), rb.max()), (None, None));
+    }
+
+    #[test]
+    fn zero_capacity_test() {
+        let mut rb = MonotonicRingBuffer::with_capacity(0);
+        assert_eq!(rb.push(1), Some(1));
+        assert_eq!((rb.min(), rb.max()), (None, None));
+    }
+}
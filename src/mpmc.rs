@@ -0,0 +1,253 @@
+//! A lock-free bounded multi-producer multi-consumer queue.
+//!
+//! This is Dmitry Vyukov's bounded queue: every slot carries a stamp telling
+//! which lap of the ring it belongs to and whether it holds a value, so
+//! producers and consumers only contend on their own index. Stamps count
+//! twice per lap (free, then full), which keeps a one-slot queue correct.
+
+use std::mem::MaybeUninit;
+
+use crate::padded::CachePadded;
+use crate::sync::{spin_loop, AtomicU64, Ordering, UnsafeCell};
+use crate::wait::WaitStrategy;
+
+struct Slot<T> {
+    stamp: AtomicU64, // 2 * seq while free for `seq`, 2 * seq + 1 once it holds it
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Share it between threads with an `Arc` or a scoped borrow.
+pub struct Queue<T> {
+    head: CachePadded<AtomicU64>, // Sequence number of the next element to pop
+    tail: CachePadded<AtomicU64>, // Sequence number of the next element to push
+    buffer: Box<[Slot<T>]>,
+}
+
+// A slot's value is only accessed by the thread that won its index with a
+// compare-exchange, and handed over through the stamp.
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        Queue {
+            head: CachePadded::new(AtomicU64::new(0)),
+            tail: CachePadded::new(AtomicU64::new(0)),
+            buffer: (0..cap as u64)
+                .map(|seq| Slot {
+                    stamp: AtomicU64::new(2 * seq),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// A snapshot that may already be stale when other threads are active.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.saturating_sub(head) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    fn slot(&self, seq: u64) -> &Slot<T> {
+        &self.buffer[(seq % self.buffer.len() as u64) as usize]
+    }
+
+    /// Hands the element back if the queue is full.
+    pub fn push(&self, element: T) -> Result<(), T> {
+        if self.buffer.is_empty() {
+            return Err(element);
+        }
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(tail);
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == 2 * tail {
+                match self.tail.compare_exchange_weak(
+                    tail,
+                    tail + 1,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.value
+                            .with_mut(|value| unsafe { (*value).write(element) });
+                        slot.stamp.store(2 * tail + 1, Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => tail = current,
+                }
+            } else if stamp < 2 * tail {
+                return Err(element); // The slot still holds last lap's element
+            } else {
+                spin_loop(); // Another producer took this lap's slot
+                tail = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = self.slot(head);
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == 2 * head + 1 {
+                match self.head.compare_exchange_weak(
+                    head,
+                    head + 1,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let element = slot
+                            .value
+                            .with(|value| unsafe { (*value).assume_init_read() });
+                        let next = head + self.buffer.len() as u64;
+                        slot.stamp.store(2 * next, Ordering::Release);
+                        return Some(element);
+                    }
+                    Err(current) => head = current,
+                }
+            } else if stamp < 2 * head + 1 {
+                return None; // Nothing has been pushed to this slot yet
+            } else {
+                spin_loop();
+                head = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Waits as `strategy` says until there is room for the element.
+    ///
+    /// # Panics
+    ///
+    /// If the queue has zero capacity, since it would never have room.
+    pub fn push_blocking(&self, element: T, strategy: WaitStrategy) {
+        assert!(self.capacity() != 0, "a zero-capacity queue never has room");
+        let mut pending = Some(element);
+        strategy.wait_until(|| match self.push(pending.take()?) {
+            Ok(()) => Some(()),
+            Err(element) => {
+                pending = Some(element);
+                None
+            }
+        })
+    }
+
+    /// Waits as `strategy` says until there is an element to pop.
+    pub fn pop_blocking(&self, strategy: WaitStrategy) -> T {
+        strategy.wait_until(|| self.pop())
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn push_pop_test() {
+        let queue = Queue::with_capacity(1);
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Err(2));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.push(2), Ok(()));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), None);
+
+        let queue = Queue::with_capacity(0);
+        assert_eq!(queue.push(1), Err(1));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_capacity_blocking_test() {
+        Queue::with_capacity(0).push_blocking(1, WaitStrategy::Spin);
+    }
+
+    #[test]
+    fn threads_test() {
+        let queue = Arc::new(Queue::with_capacity(5));
+        let producers = (0..4u64)
+            .map(|p| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        queue.push_blocking(
+                            p * 1000 + i,
+                            WaitStrategy::Park(Duration::from_micros(10)),
+                        );
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let consumers = (0..4)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    (0..1000)
+                        .map(|_| queue.pop_blocking(WaitStrategy::SpinThenYield))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        producers.into_iter().for_each(|h| h.join().unwrap());
+        let mut all = consumers
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>();
+        all.sort_unstable();
+        assert_eq!(all, (0..4000).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use crate::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn producers_test() {
+        loom::model(|| {
+            let queue = Arc::new(Queue::with_capacity(2));
+            let producers = (0..2)
+                .map(|i| {
+                    let queue = Arc::clone(&queue);
+                    thread::spawn(move || queue.push(i).unwrap())
+                })
+                .collect::<Vec<_>>();
+            let mut popped = queue.pop().into_iter().collect::<Vec<_>>();
+            producers.into_iter().for_each(|h| h.join().unwrap());
+            popped.extend(queue.pop());
+            popped.extend(queue.pop());
+            popped.sort_unstable();
+            assert_eq!(popped, [0, 1]);
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use crate::sync::{fence, AtomicBool, Mutex, Ordering};
+use std::task::Waker;
+
+/// Holds the waker of a task waiting on the other side of a buffer.
+///
+/// The waiting side registers and then checks the buffer again; the other
+/// side changes the buffer and then wakes. The fences guarantee that at least
+/// one of them sees the other's change, so no wakeup is lost.
+pub(crate) struct WakerSlot {
+    waker: Mutex<Option<Waker>>,
+    armed: AtomicBool, // Lets `wake` skip the lock when nobody waits
+}
+
+impl WakerSlot {
+    pub(crate) fn new() -> Self {
+        WakerSlot {
+            waker: Mutex::new(None),
+            armed: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut slot = self.waker.lock().unwrap();
+        if !slot.as_ref().is_some_and(|old| old.will_wake(waker)) {
+            *slot = Some(waker.clone());
+        }
+        self.armed.store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+    }
+
+    pub(crate) fn wake(&self) {
+        fence(Ordering::SeqCst);
+        if self.armed.swap(false, Ordering::Relaxed) {
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+    use std::task::Wake;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn no_lost_wakeup_test() {
+        loom::model(|| {
+            let slot = Arc::new(WakerSlot::new());
+            let ready = Arc::new(AtomicBool::new(false));
+            let flag = std::sync::Arc::new(Flag(AtomicBool::new(false)));
+            let handle = {
+                let (slot, ready) = (Arc::clone(&slot), Arc::clone(&ready));
+                thread::spawn(move || {
+                    ready.store(true, Ordering::Release);
+                    slot.wake();
+                })
+            };
+            slot.register(&Waker::from(std::sync::Arc::clone(&flag)));
+            let seen = ready.load(Ordering::Acquire);
+            handle.join().unwrap();
+            assert!(seen || flag.0.load(Ordering::Relaxed));
+        });
+    }
+}
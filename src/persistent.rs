@@ -0,0 +1,505 @@
+//! A circular log of byte records kept in a file of bounded size.
+//!
+//! The file holds a header followed by a data region of `capacity` bytes.
+//! Every record is stored as its little-endian `u32` length and CRC-32
+//! followed by its bytes, and may wrap around the end of the region. Once a
+//! new record does not fit, the oldest records are dropped to make room, so
+//! the file always holds the most recent ones and survives restarts.
+//!
+//! The header is the commit marker: a record only becomes part of the log
+//! once a header that includes it has been written. There are two header
+//! slots, written alternately and each with its own checksum, so a torn
+//! header write leaves the previous one in place. Evictions are committed
+//! before the new record overwrites them. A process that crashes at any point
+//! therefore loses at most the record it was writing.
+//!
+//! The operating system may still reorder writes that have not been synced,
+//! so after a power loss records written since the last `sync` can be torn.
+//! `open` checks every record against its checksum and drops damaged ones;
+//! `skipped` tells how many were lost that way. The intact records are
+//! copied to a new log next to the old one, `<path>.recovering`, which is
+//! then renamed over it, so a crash during recovery leaves the old log to
+//! recover again.
+
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"circuslg");
+const VERSION: u64 = 2;
+const SLOT: u64 = 64; // Size of one header slot
+const HEADER: u64 = 2 * SLOT;
+const PREFIX: u64 = 8; // A record's length and checksum
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 as used by zlib and Ethernet.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+pub struct PersistentRingBuffer {
+    file: File,
+    capacity: u64, // Size of the data region in bytes
+    head: u64,     // Offset of the oldest record in the data region
+    used: u64,     // Bytes taken by records, prefixes included
+    len: u64,      // Number of records
+    seq: u64,      // Incremented on every header write, picks the slot
+    skipped: usize,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl PersistentRingBuffer {
+    /// Opens the log at `path`, creating it with `capacity` bytes of room for
+    /// records if the file is missing or empty.
+    pub fn open<P: AsRef<Path>>(path: P, capacity: u64) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut rb = PersistentRingBuffer {
+            file,
+            capacity,
+            head: 0,
+            used: 0,
+            len: 0,
+            seq: 0,
+            skipped: 0,
+        };
+        if rb.file.metadata()?.len() == 0 {
+            rb.file.set_len(HEADER + capacity)?;
+            rb.write_header()?;
+            return Ok(rb);
+        }
+        let fields = (0..2)
+            .filter_map(|slot| rb.read_header(slot).transpose())
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .max_by_key(|fields| fields[6])
+            .ok_or_else(|| invalid("no valid header"))?;
+        if fields[0] != MAGIC || fields[1] != VERSION {
+            return Err(invalid("not a ring buffer log, or made by another version"));
+        }
+        if fields[2] != capacity {
+            return Err(invalid("log was created with another capacity"));
+        }
+        rb.head = fields[3];
+        rb.used = fields[4];
+        rb.len = fields[5];
+        rb.seq = fields[6];
+        if rb.head >= capacity.max(1) || rb.used > capacity {
+            return Err(invalid("corrupt header"));
+        }
+        rb.recover(path)?;
+        Ok(rb)
+    }
+
+    /// Room for records in bytes, length prefixes included.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of damaged records `open` dropped.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Appends a record, dropping the oldest ones until it fits. Returns how
+    /// many were dropped.
+    pub fn push(&mut self, record: &[u8]) -> io::Result<usize> {
+        let size = PREFIX + record.len() as u64;
+        if size > self.capacity || record.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "record is larger than the log",
+            ));
+        }
+        let mut dropped = 0;
+        while self.capacity - self.used < size {
+            self.skip_front()?;
+            dropped += 1;
+        }
+        if dropped > 0 {
+            self.write_header()?;
+        }
+        self.write_record(record)?;
+        self.write_header()?;
+        Ok(dropped)
+    }
+
+    /// Removes and returns the oldest record.
+    pub fn pop(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        let record = self.read_record(self.head)?;
+        self.skip_front()?;
+        self.write_header()?;
+        Ok(Some(record))
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.head = 0;
+        self.used = 0;
+        self.len = 0;
+        self.write_header()
+    }
+
+    /// Reads every record from oldest to newest.
+    pub fn iter(&mut self) -> Records<'_> {
+        Records {
+            at: self.head,
+            remaining: self.len,
+            log: self,
+        }
+    }
+
+    /// Flushes records and header to the disk.
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    fn offset(&self, from_head: u64) -> u64 {
+        (self.head + from_head) % self.capacity
+    }
+
+    fn record_len(&mut self, at: u64) -> io::Result<u64> {
+        let mut len = [0; 4];
+        self.read_at(at, &mut len)?;
+        Ok(u32::from_le_bytes(len) as u64)
+    }
+
+    fn read_record(&mut self, at: u64) -> io::Result<Vec<u8>> {
+        let mut prefix = [0; PREFIX as usize];
+        self.read_at(at, &mut prefix)?;
+        let (len, crc) = prefix.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if len as u64 + PREFIX > self.capacity {
+            return Err(invalid("record length is out of bounds"));
+        }
+        let mut record = vec![0; len];
+        self.read_at((at + PREFIX) % self.capacity, &mut record)?;
+        if crc32(&record) != u32::from_le_bytes(crc.try_into().unwrap()) {
+            return Err(invalid("record checksum does not match"));
+        }
+        Ok(record)
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        let mut prefix = [0; PREFIX as usize];
+        prefix[..4].copy_from_slice(&(record.len() as u32).to_le_bytes());
+        prefix[4..].copy_from_slice(&crc32(record).to_le_bytes());
+        self.write_at(self.offset(self.used), &prefix)?;
+        self.write_at(self.offset(self.used + PREFIX), record)?;
+        self.used += PREFIX + record.len() as u64;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn skip_front(&mut self) -> io::Result<()> {
+        let size = PREFIX + self.record_len(self.head)?;
+        self.head = self.offset(size);
+        self.used -= size;
+        self.len -= 1;
+        if self.len == 0 {
+            self.head = 0;
+            self.used = 0;
+        }
+        Ok(())
+    }
+
+    // Walks the committed records and, if any are damaged, replaces the log
+    // with a copy of the ones that are intact. A record whose length is still
+    // plausible is skipped on its own; a bad length loses every record after
+    // it too, since their positions are unknown.
+    fn recover(&mut self, path: &Path) -> io::Result<()> {
+        let mut intact = Vec::new();
+        let mut at = self.head;
+        let mut walked = 0;
+        for _ in 0..self.len {
+            let size = PREFIX + self.record_len(at)?;
+            if walked + size > self.used {
+                break;
+            }
+            if let Ok(record) = self.read_record(at) {
+                intact.push(record);
+            }
+            at = (at + size) % self.capacity;
+            walked += size;
+        }
+        if intact.len() as u64 == self.len && walked == self.used {
+            return Ok(());
+        }
+        // Compacting in place could overwrite records the old header still
+        // points to, so the copy goes to a file of its own until it is whole
+        let mut staging = path.as_os_str().to_owned();
+        staging.push(".recovering");
+        match fs::remove_file(&staging) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let mut copy = PersistentRingBuffer::open(&staging, self.capacity)?;
+        for record in &intact {
+            copy.push(record)?;
+        }
+        copy.sync()?;
+        copy.skipped = self.len as usize - intact.len();
+        *self = copy;
+        fs::rename(&staging, path)?;
+        sync_parent(path)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        self.seq += 1;
+        let mut header = [0; SLOT as usize];
+        let fields = [
+            MAGIC,
+            VERSION,
+            self.capacity,
+            self.head,
+            self.used,
+            self.len,
+            self.seq,
+        ];
+        for (i, field) in fields.iter().enumerate() {
+            header[i * 8..(i + 1) * 8].copy_from_slice(&field.to_le_bytes());
+        }
+        let crc = crc32(&header[..56]);
+        header[56..60].copy_from_slice(&crc.to_le_bytes());
+        self.file.seek(SeekFrom::Start(self.seq % 2 * SLOT))?;
+        self.file.write_all(&header)
+    }
+
+    // Returns `None` for a slot that was never written or was torn.
+    fn read_header(&mut self, slot: u64) -> io::Result<Option<[u64; 7]>> {
+        let mut header = [0; SLOT as usize];
+        self.file.seek(SeekFrom::Start(slot * SLOT))?;
+        self.file.read_exact(&mut header)?;
+        if crc32(&header[..56]).to_le_bytes() != header[56..60] {
+            return Ok(None);
+        }
+        let mut fields = [0; 7];
+        for (i, field) in fields.iter_mut().enumerate() {
+            *field = u64::from_le_bytes(header[i * 8..(i + 1) * 8].try_into().unwrap());
+        }
+        Ok(Some(fields))
+    }
+
+    // `at` is an offset in the data region; the access wraps around its end.
+    fn read_at(&mut self, at: u64, buf: &mut [u8]) -> io::Result<()> {
+        let first = (buf.len() as u64).min(self.capacity - at) as usize;
+        let (front, back) = buf.split_at_mut(first);
+        self.file.seek(SeekFrom::Start(HEADER + at))?;
+        self.file.read_exact(front)?;
+        self.file.seek(SeekFrom::Start(HEADER))?;
+        self.file.read_exact(back)
+    }
+
+    fn write_at(&mut self, at: u64, buf: &[u8]) -> io::Result<()> {
+        let first = (buf.len() as u64).min(self.capacity - at) as usize;
+        let (front, back) = buf.split_at(first);
+        self.file.seek(SeekFrom::Start(HEADER + at))?;
+        self.file.write_all(front)?;
+        self.file.seek(SeekFrom::Start(HEADER))?;
+        self.file.write_all(back)
+    }
+}
+
+// Makes a rename in the directory durable. Only Unix can open a directory to
+// sync it.
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Reads records from oldest to newest, see `PersistentRingBuffer::iter`.
+pub struct Records<'a> {
+    log: &'a mut PersistentRingBuffer,
+    at: u64,
+    remaining: u64,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let record = self.log.read_record(self.at);
+        if let Ok(record) = &record {
+            self.at = (self.at + PREFIX + record.len() as u64) % self.log.capacity;
+        } else {
+            self.remaining = 0;
+        }
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("circus-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn records(log: &mut PersistentRingBuffer) -> Vec<Vec<u8>> {
+        log.iter().collect::<io::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn wrap_test() {
+        let path = temp_path("wrap");
+        let mut log = PersistentRingBuffer::open(&path, 28).unwrap();
+        assert_eq!(log.push(b"abcd").unwrap(), 0);
+        assert_eq!(log.push(b"efgh").unwrap(), 0);
+        assert_eq!(log.push(b"ij").unwrap(), 1); // Wraps and drops "abcd"
+        assert_eq!(records(&mut log), vec![b"efgh".to_vec(), b"ij".to_vec()]);
+        assert_eq!(log.pop().unwrap(), Some(b"efgh".to_vec()));
+        assert_eq!(log.push(b"klmnopq").unwrap(), 0);
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            log.push(&[0; 21]).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reopen_test() {
+        let path = temp_path("reopen");
+        let mut log = PersistentRingBuffer::open(&path, 48).unwrap();
+        for i in 0..10u8 {
+            log.push(&[i; 3]).unwrap();
+        }
+        drop(log);
+        let mut log = PersistentRingBuffer::open(&path, 48).unwrap();
+        assert_eq!(
+            records(&mut log),
+            (6..10u8).map(|i| vec![i; 3]).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            PersistentRingBuffer::open(&path, 64).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    fn corrupt(path: &Path, at: u64) {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        let mut byte = [0];
+        file.seek(SeekFrom::Start(at)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::Start(at)).unwrap();
+        file.write_all(&[!byte[0]]).unwrap();
+    }
+
+    #[test]
+    fn recovery_test() {
+        let path = temp_path("recovery");
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        for record in [b"abc", b"def", b"ghi"].iter() {
+            log.push(*record).unwrap();
+        }
+        drop(log);
+        corrupt(&path, HEADER + 11 + PREFIX); // First byte of "def"
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        assert_eq!(log.skipped(), 1);
+        assert_eq!(records(&mut log), vec![b"abc".to_vec(), b"ghi".to_vec()]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn interrupted_recovery_test() {
+        let path = temp_path("interrupted");
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        for record in [b"abc", b"def", b"ghi"].iter() {
+            log.push(*record).unwrap();
+        }
+        drop(log);
+        corrupt(&path, HEADER + 11 + PREFIX);
+        // A directory in the way makes recovery fail as if it had crashed
+        // while writing the copy
+        let mut staging = path.clone().into_os_string();
+        staging.push(".recovering");
+        let staging = PathBuf::from(staging);
+        fs::create_dir(&staging).unwrap();
+        assert!(PersistentRingBuffer::open(&path, 64).is_err());
+        fs::remove_dir(&staging).unwrap();
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        assert_eq!(log.skipped(), 1);
+        assert_eq!(records(&mut log), vec![b"abc".to_vec(), b"ghi".to_vec()]);
+        assert!(!staging.exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn torn_header_test() {
+        let path = temp_path("torn");
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        log.push(b"abc").unwrap();
+        log.push(b"def").unwrap(); // Committed with sequence 3, in slot 1
+        drop(log);
+        corrupt(&path, SLOT + 8);
+        let mut log = PersistentRingBuffer::open(&path, 64).unwrap();
+        assert_eq!(log.skipped(), 0);
+        assert_eq!(records(&mut log), vec![b"abc".to_vec()]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+//! Generates buffers with a random capacity, random contents and a random
+//! position of the oldest element in storage. Only `RingBufferU` with its
+//! default storage is covered, since `quickcheck` needs `Clone`.
+
+use crate::ringbufferu::RingBufferU;
+use quickcheck::{Arbitrary, Gen};
+
+const MAX_SPARE: usize = 8; // Free slots added on top of the contents
+
+impl<T: Arbitrary> Arbitrary for RingBufferU<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let elements = Vec::<T>::arbitrary(g);
+        let spare = usize::arbitrary(g) % (MAX_SPARE + 1);
+        let mut rb = RingBufferU::with_capacity(elements.len() + spare);
+        if rb.capacity() > 0 {
+            rb.set_start(usize::arbitrary(g) % rb.capacity());
+        }
+        rb.extend(elements);
+        rb
+    }
+
+    /// Shrinks the contents and keeps the capacity.
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let cap = self.capacity();
+        let elements = self.iter().cloned().collect::<Vec<_>>();
+        Box::new(elements.shrink().map(move |elements| {
+            let mut rb = RingBufferU::with_capacity(cap);
+            rb.extend(elements);
+            rb
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ringbuffer::RingBuffer;
+    use quickcheck::QuickCheck;
+
+    #[test]
+    fn quickcheck_test() {
+        fn round_trip(rb: RingBuffer<u8>) -> bool {
+            let elements = rb.iter().copied().collect::<Vec<_>>();
+            rb.len() <= rb.capacity() && rb.into_iter().eq(elements)
+        }
+        QuickCheck::new().quickcheck(round_trip as fn(RingBuffer<u8>) -> bool);
+    }
+}
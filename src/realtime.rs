@@ -0,0 +1,173 @@
+//! A ring buffer for real-time threads such as audio callbacks.
+//!
+//! `RtRingBuffer` allocates its storage once, in `with_capacity`, and then
+//! only offers operations that neither allocate nor panic:
+//!
+//! - `push`, `try_push`, `pop`, `pop_back` and `pop_into` move single
+//!   elements, `extend_from_slice` copies slices of `Copy` elements;
+//! - `front`, `back`, `get`, `get_mut`, `as_slices` and `iter` only look;
+//! - `len`, `is_empty`, `is_full`, `capacity` and `clear` are plain
+//!   bookkeeping.
+//!
+//! Everything that may grow or reallocate the storage is left out. Popped
+//! and overwritten elements are handed to the caller instead of dropped, so
+//! a `T` that frees memory on drop can be sent elsewhere to be dropped. Only
+//! `clear` and dropping the buffer itself run destructors in place.
+//!
+//! With the `mlock` feature on Linux, `lock_memory` also keeps the storage
+//! resident, so touching it never waits for a page fault.
+
+use crate::ringbufferu::{RBURefIter, RingBufferU};
+use std::mem::MaybeUninit;
+
+type Slots<T> = Box<[MaybeUninit<T>]>;
+
+pub struct RtRingBuffer<T> {
+    buffer: RingBufferU<T, Slots<T>>,
+    locked: bool,
+}
+
+impl<T> RtRingBuffer<T> {
+    /// Allocates every slot up front; this is the only allocation the buffer
+    /// ever makes.
+    pub fn with_capacity(cap: usize) -> Self {
+        let slots = (0..cap)
+            .map(|_| MaybeUninit::uninit())
+            .collect::<Slots<T>>();
+        RtRingBuffer {
+            buffer: RingBufferU::from_storage(slots),
+            locked: false,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    /// Returns the oldest element if it had to be overwritten.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        self.buffer.push(element)
+    }
+
+    pub fn try_push(&mut self, element: T) -> Result<(), T> {
+        self.buffer.try_push(element)
+    }
+
+    pub fn extend_from_slice(&mut self, src: &[T])
+    where
+        T: Copy,
+    {
+        self.buffer.extend_from_slice(src);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.buffer.pop()
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.buffer.pop_back()
+    }
+
+    /// Pops elements into `dst` until either runs out, returning how many
+    /// were written. The elements `dst` held before are dropped.
+    pub fn pop_into(&mut self, dst: &mut [T]) -> usize {
+        self.buffer.pop_into(dst)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.buffer.front()
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.buffer.back()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.buffer.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.buffer.get_mut(index)
+    }
+
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        self.buffer.as_slices()
+    }
+
+    pub fn iter(&self) -> RBURefIter<'_, T, Slots<T>> {
+        self.buffer.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Gives up the real-time guarantees for the full `RingBufferU` API.
+    pub fn into_inner(mut self) -> RingBufferU<T, Slots<T>> {
+        self.unlock();
+        let empty = RingBufferU::from_storage(Slots::default());
+        std::mem::replace(&mut self.buffer, empty)
+    }
+
+    #[cfg(all(feature = "mlock", target_os = "linux"))]
+    fn storage(&self) -> (*const u8, usize) {
+        let slots = self.buffer.storage();
+        (slots.as_ptr().cast(), std::mem::size_of_val(&slots[..]))
+    }
+
+    /// Locks the storage into RAM. Fails when the process may not lock that
+    /// much memory, see `RLIMIT_MEMLOCK`.
+    #[cfg(all(feature = "mlock", target_os = "linux"))]
+    pub fn lock_memory(&mut self) -> std::io::Result<()> {
+        let (ptr, len) = self.storage();
+        if len == 0 || self.locked {
+            return Ok(());
+        }
+        if unsafe { libc::mlock(ptr.cast(), len) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        self.locked = true;
+        Ok(())
+    }
+
+    fn unlock(&mut self) {
+        #[cfg(all(feature = "mlock", target_os = "linux"))]
+        if self.locked {
+            let (ptr, len) = self.storage();
+            unsafe { libc::munlock(ptr.cast(), len) };
+        }
+        self.locked = false;
+    }
+}
+
+impl<T> Drop for RtRingBuffer<T> {
+    fn drop(&mut self) {
+        self.unlock();
+    }
+}
+
+#[cfg(all(test, feature = "mlock", target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_memory_test() {
+        let mut rb = RtRingBuffer::with_capacity(1024);
+        rb.lock_memory().unwrap();
+        rb.push(1u64);
+        let mut rb = rb.into_inner();
+        assert_eq!(rb.pop(), Some(1));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::ringbuffer::{RBRefIter, RingBuffer};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// xorshift64*: small and fast, good enough for picking sample slots.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // One splitmix64 round so that nearby seeds give unrelated streams
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        XorShift((z ^ (z >> 31)) | 1) // The state must never be zero
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % bound;
+            }
+        }
+    }
+}
+
+/// Keeps a uniform random sample of `capacity` elements out of everything
+/// ever pushed (Algorithm R), instead of the newest ones.
+pub struct ReservoirBuffer<T> {
+    buffer: RingBuffer<T>,
+    seen: u64,
+    rng: XorShift,
+}
+
+impl<T> ReservoirBuffer<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::with_seed(cap, seed)
+    }
+
+    /// Like `with_capacity`, but samples reproducibly for a given seed.
+    pub fn with_seed(cap: usize, seed: u64) -> Self {
+        ReservoirBuffer {
+            buffer: RingBuffer::with_capacity(cap),
+            seen: 0,
+            rng: XorShift::new(seed),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Number of elements offered to the reservoir so far.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Offers `element` to the sample. Returns whichever element did not make
+    /// it: the one it replaced, or `element` itself if it was not selected.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        self.seen += 1;
+        if !self.buffer.is_full() {
+            self.buffer.push(element);
+            return None;
+        }
+        let slot = self.rng.below(self.seen);
+        if slot < self.capacity() as u64 {
+            Some(std::mem::replace(&mut self.buffer[slot as usize], element))
+        } else {
+            Some(element)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.seen = 0;
+    }
+
+    pub fn iter(&self) -> RBRefIter<'_, T> {
+        self.buffer.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.buffer.into_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservoir_fill_test() {
+        let mut reservoir = ReservoirBuffer::with_seed(3, 7);
+        for i in 0..3 {
+            assert_eq!(reservoir.push(i), None);
+        }
+        assert_eq!(reservoir.iter().collect::<Vec<_>>(), vec![&0, &1, &2]);
+        let discarded = reservoir.push(3).unwrap();
+        assert_eq!(reservoir.len(), 3);
+        assert_eq!(reservoir.seen(), 4);
+        let mut all = reservoir.into_vec();
+        all.push(discarded);
+        all.sort();
+        assert_eq!(all, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn reservoir_uniform_test() {
+        let mut hits = [0u32; 10];
+        for seed in 0..2000 {
+            let mut reservoir = ReservoirBuffer::with_seed(2, seed);
+            for i in 0..10 {
+                reservoir.push(i);
+            }
+            for &i in reservoir.iter() {
+                hits[i] += 1;
+            }
+        }
+        // Each element is kept with probability 2 / 10, i.e. about 400 times
+        assert!(hits.iter().all(|&h| (300..500).contains(&h)), "{:?}", hits);
+    }
+}
@@ -1,43 +1,353 @@
-use std::mem::{replace, MaybeUninit};
+use crate::cursor::{Cursor, CursorError};
+use crate::snapshot::Snapshot;
+use crate::storage::{AlignedStorage, Storage};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{Chain, FromIterator, FusedIterator, Map, StepBy};
+use std::marker::PhantomData;
+use std::mem::{replace, size_of, MaybeUninit};
+use std::ops::{Index, IndexMut, Range};
+use std::ptr;
+use std::slice::IterMut;
 
-pub struct RingBufferU<T> {
-    start: usize,
+/// How many elements `fill_with` puts in a buffer of zero-sized types,
+/// whose capacity is too large to fill or to drop again.
+pub const ZST_FILL_LIMIT: usize = 1 << 16;
+
+unsafe fn slice_assume_init_ref<T>(slice: &[MaybeUninit<T>]) -> &[T] {
+    &*(slice as *const [MaybeUninit<T>] as *const [T])
+}
+
+unsafe fn slice_assume_init_mut<T>(slice: &mut [MaybeUninit<T>]) -> &mut [T] {
+    &mut *(slice as *mut [MaybeUninit<T>] as *mut [T])
+}
+
+/// Returned by `RingBufferU::stats` with the `op-stats` feature. Counts are
+/// of elements since the buffer was created, and satisfy
+/// `pushed == popped + overwritten + len`.
+#[cfg(feature = "op-stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpStats {
+    /// Elements that entered the buffer, or were turned away by a buffer
+    /// with zero capacity.
+    pub pushed: u64,
+    /// Elements taken out other than by overwriting: popped, removed,
+    /// drained, truncated or cleared.
+    pub popped: u64,
+    pub overwritten: u64,
+    pub len: usize,
+    /// The highest `len` reached.
+    pub peak_len: usize,
+}
+
+/// Which end of a full buffer gives up an element to make room, for the
+/// `insert_sorted` family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum End {
+    Front,
+    Back,
+}
+
+pub struct RingBufferU<T, S: Storage<T> = Vec<MaybeUninit<T>>> {
+    start: usize, // Slot of the front element, always below the capacity
     size: usize,
-    buffer: Vec<MaybeUninit<T>>,
+    seq: usize, // Sequence number of the front element, for cursors; wraps around
+    overwritten: u64,
+    overwritten_reset: u64, // Value of `overwritten` at the last reset
+    #[cfg(feature = "op-stats")]
+    pushed: u64,
+    #[cfg(feature = "op-stats")]
+    peak_len: usize,
+    buffer: S,
+    marker: PhantomData<T>,
 }
 
 impl<T> RingBufferU<T> {
     pub fn with_capacity(cap: usize) -> Self {
-        let buffer = {
-            let mut buffer = Vec::with_capacity(cap);
-            for _ in 0..cap {
-                buffer.push(MaybeUninit::uninit());
-            }
-            buffer
+        let mut buffer = Vec::with_capacity(cap);
+        // Zero-sized slots need no memory, so use every one the Vec offers
+        let len = if size_of::<T>() == 0 {
+            buffer.capacity()
+        } else {
+            cap
         };
+        // Uninitialized slots are valid `MaybeUninit`s, so the memory is
+        // left untouched until elements are pushed
+        unsafe { buffer.set_len(len) };
+        Self::from_storage(buffer)
+    }
+
+    /// Rounds `cap` up to a power of two, so slot indices are computed with
+    /// a bit mask instead of a division.
+    pub fn with_capacity_pow2(cap: usize) -> Self {
+        let cap = cap.checked_next_power_of_two().expect("capacity overflow");
+        Self::with_capacity(cap)
+    }
+
+    /// Reallocates the storage to hold `cap` elements, keeping the logical
+    /// contents. When shrinking, the oldest elements are dropped.
+    pub fn set_capacity(&mut self, cap: usize) {
+        let mut resized = Self::with_capacity(cap);
+        resized.seq = self.seq;
+        resized.overwritten = self.overwritten;
+        resized.overwritten_reset = self.overwritten_reset;
+        resized.extend(self.drain());
+        resized.copy_stats(self);
+        *self = resized;
+    }
+
+    /// Grows the capacity so that `additional` more elements fit without
+    /// overwriting. May reserve more space to amortize repeated growth.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .size
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.capacity() {
+            self.set_capacity(needed.max(self.capacity().saturating_mul(2)));
+        }
+    }
+
+    /// Like `reserve`, but grows to exactly the needed capacity.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let needed = self
+            .size
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.capacity() {
+            self.set_capacity(needed);
+        }
+    }
+
+    /// Shrinks the capacity down to the current length; no elements are dropped.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    /// Shrinks the capacity to at least `min_capacity` and at least the
+    /// current length; no elements are dropped.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let target = self.size.max(min_capacity);
+        if target < self.capacity() {
+            self.set_capacity(target);
+        }
+    }
+}
+
+#[cfg(feature = "allocator-api2")]
+impl<T, A: allocator_api2::alloc::Allocator>
+    RingBufferU<T, allocator_api2::vec::Vec<MaybeUninit<T>, A>>
+{
+    /// Like `with_capacity`, but takes the storage from `alloc`.
+    pub fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        let mut buffer = allocator_api2::vec::Vec::with_capacity_in(cap, alloc);
+        let len = if size_of::<T>() == 0 {
+            buffer.capacity()
+        } else {
+            cap
+        };
+        unsafe { buffer.set_len(len) };
+        Self::from_storage(buffer)
+    }
+}
+
+impl<T> RingBufferU<T, AlignedStorage<T>> {
+    /// Like `with_capacity`, but the storage starts on an `align`-byte
+    /// boundary; see `AlignedStorage`.
+    pub fn with_capacity_aligned(cap: usize, align: usize) -> Self {
+        Self::from_storage(AlignedStorage::new(cap, align))
+    }
+}
+
+impl<T, S: Storage<T>> RingBufferU<T, S> {
+    /// Builds an empty buffer with one slot per element of `storage`. Any
+    /// values already in the storage are ignored and never dropped.
+    pub fn from_storage(storage: S) -> Self {
         RingBufferU {
             start: 0,
             size: 0,
-            buffer,
+            seq: 0,
+            overwritten: 0,
+            overwritten_reset: 0,
+            #[cfg(feature = "op-stats")]
+            pushed: 0,
+            #[cfg(feature = "op-stats")]
+            peak_len: 0,
+            buffer: storage,
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn storage(&self) -> &S {
+        &self.buffer
+    }
+
+    /// Number of elements lost because the buffer was full, since it was
+    /// created: overwritten by pushes and inserts, dropped by
+    /// `set_capacity`, or turned away by a buffer with zero capacity.
+    /// Elements handed back by `try_push` are not lost and not counted.
+    pub fn overwritten(&self) -> u64 {
+        self.overwritten
+    }
+
+    /// Like `overwritten`, but only since the last `reset_overwritten`.
+    pub fn overwritten_since_reset(&self) -> u64 {
+        self.overwritten - self.overwritten_reset
+    }
+
+    /// Restarts `overwritten_since_reset` from zero, returning its value.
+    pub fn reset_overwritten(&mut self) -> u64 {
+        let since = self.overwritten_since_reset();
+        self.overwritten_reset = self.overwritten;
+        since
+    }
+
+    fn count_overwritten(&mut self, n: usize) {
+        self.overwritten += n as u64;
+    }
+
+    /// Cumulative operation counts, for monitoring a buffer without
+    /// wrapping it.
+    #[cfg(feature = "op-stats")]
+    pub fn stats(&self) -> OpStats {
+        OpStats {
+            pushed: self.pushed,
+            popped: self.pushed - self.overwritten - self.size as u64,
+            overwritten: self.overwritten,
+            len: self.size,
+            peak_len: self.peak_len,
+        }
+    }
+
+    // Called after the elements are in, so that the peak includes them
+    fn count_pushed(&mut self, n: usize) {
+        #[cfg(feature = "op-stats")]
+        {
+            self.pushed += n as u64;
+            self.peak_len = self.peak_len.max(self.size);
+        }
+        let _ = n;
+    }
+
+    // For operations that take elements out and push them back in place
+    fn uncount_pushed(&mut self, n: usize) {
+        #[cfg(feature = "op-stats")]
+        {
+            self.pushed -= n as u64;
         }
+        let _ = n;
+    }
+
+    // The new buffer of `set_capacity` or `clone` has only pushed what was
+    // left in `other`; the history is `other`'s.
+    fn copy_stats<S2: Storage<T>>(&mut self, other: &RingBufferU<T, S2>) {
+        #[cfg(feature = "op-stats")]
+        {
+            self.pushed = other.pushed;
+            self.peak_len = other.peak_len;
+        }
+        let _ = other;
     }
 
     pub fn capacity(&self) -> usize {
-        self.buffer.capacity()
+        self.buffer.as_slice().len()
     }
 
-    pub fn push(&mut self, element: T) {
-        let idx = (self.start + self.size) % self.capacity();
-        if self.size == self.capacity() {
-            unsafe {
-                let _ =
-                    replace(self.buffer.get_mut(idx).unwrap(), MaybeUninit::uninit()).assume_init();
-            } // Drop element that will be overwritten
-            self.start += 1
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.size == self.capacity()
+    }
+
+    // `start` is always kept below the capacity and `offset` never exceeds
+    // it, so neither branch can overflow however long the buffer runs
+    fn index_of(&self, offset: usize) -> usize {
+        let cap = self.capacity();
+        if cap.is_power_of_two() {
+            (self.start + offset) & (cap - 1)
+        } else if offset < cap - self.start {
+            self.start + offset
         } else {
-            self.size += 1
+            offset - (cap - self.start)
+        }
+    }
+
+    /// Pushes every element of `src` in order, with at most two memory copies.
+    pub fn extend_from_slice(&mut self, mut src: &[T])
+    where
+        T: Copy,
+    {
+        let cap = self.capacity();
+        if size_of::<T>() == 0 || cap == 0 {
+            self.extend(src.iter().copied());
+            return;
         }
-        self.buffer[idx] = MaybeUninit::new(element);
+        let pushed = src.len();
+        let dropped = (self.size + src.len()).saturating_sub(cap);
+        if src.len() >= cap {
+            src = &src[src.len() - cap..]; // Everything already stored gets overwritten
+            self.start = 0;
+            self.size = 0;
+        }
+        let end = self.index_of(self.size);
+        let first = src.len().min(cap - end);
+        unsafe {
+            let dst = self.buffer.as_mut_slice().as_mut_ptr() as *mut T;
+            ptr::copy_nonoverlapping(src.as_ptr(), dst.add(end), first);
+            ptr::copy_nonoverlapping(src.as_ptr().add(first), dst, src.len() - first);
+        }
+        let overflow = (self.size + src.len()).saturating_sub(cap);
+        self.start = self.index_of(overflow);
+        self.size += src.len() - overflow;
+        self.seq = self.seq.wrapping_add(dropped);
+        self.count_overwritten(dropped);
+        self.count_pushed(pushed);
+    }
+
+    /// Returns the oldest element if it had to be overwritten. A buffer
+    /// with zero capacity stores nothing and hands `element` straight back.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        if self.capacity() == 0 {
+            self.count_overwritten(1);
+            self.count_pushed(1);
+            return Some(element);
+        }
+        let idx = self.index_of(self.size);
+        let evicted = if self.size == self.capacity() {
+            self.start = self.index_of(1);
+            self.seq = self.seq.wrapping_add(1);
+            self.count_overwritten(1);
+            Some(unsafe {
+                replace(
+                    self.buffer.as_mut_slice().get_mut(idx).unwrap(),
+                    MaybeUninit::uninit(),
+                )
+                .assume_init()
+            })
+        } else {
+            self.size += 1;
+            None
+        };
+        self.buffer.as_mut_slice()[idx] = MaybeUninit::new(element);
+        self.count_pushed(1);
+        evicted
+    }
+
+    /// Like `push`, but hands the element back instead of overwriting when full.
+    pub fn try_push(&mut self, element: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(element);
+        }
+        self.push(element);
+        Ok(())
     }
 
     pub fn pop(&mut self) -> Option<T> {
@@ -45,47 +355,909 @@ impl<T> RingBufferU<T> {
             return None;
         }
 
-        let idx = self.start;
-        self.start = (self.start + 1) % self.capacity();
+        let idx = self.index_of(0);
+        self.start = self.index_of(1);
+        self.size -= 1;
+        self.seq = self.seq.wrapping_add(1);
+        Some(unsafe {
+            replace(
+                self.buffer.as_mut_slice().get_mut(idx).unwrap(),
+                MaybeUninit::uninit(),
+            )
+            .assume_init()
+        })
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.size {
+            return None;
+        }
+        let idx = self.index_of(index);
+        Some(unsafe { self.buffer.as_slice()[idx].assume_init_ref() })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.size {
+            return None;
+        }
+        let idx = self.index_of(index);
+        Some(unsafe { self.buffer.as_mut_slice()[idx].assume_init_mut() })
+    }
+
+    /// Moves where the oldest element will be stored, so that generated
+    /// buffers also cover contents that wrap around. The buffer must be empty.
+    #[cfg(any(feature = "arbitrary", feature = "quickcheck"))]
+    pub(crate) fn set_start(&mut self, start: usize) {
+        debug_assert!(self.is_empty() && start < self.capacity().max(1));
+        self.start = start;
+    }
+
+    /// The free slots after the newest element, in push order; the second
+    /// run is non-empty when they wrap around the end of storage.
+    #[cfg(feature = "bytes")]
+    pub(crate) fn spare_slices_mut(&mut self) -> (&mut [MaybeUninit<T>], &mut [MaybeUninit<T>]) {
+        let free = self.capacity() - self.size;
+        let end = self.index_of(self.size);
+        let first = free.min(self.capacity() - end);
+        let (low, high) = self.buffer.as_mut_slice().split_at_mut(end);
+        (&mut high[..first], &mut low[..free - first])
+    }
+
+    /// Makes the first `n` spare slots part of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// Those slots must have been initialized through `spare_slices_mut`.
+    #[cfg(feature = "bytes")]
+    pub(crate) unsafe fn assume_pushed(&mut self, n: usize) {
+        debug_assert!(self.size + n <= self.capacity());
+        self.size += n;
+        self.count_pushed(n);
+    }
+
+    pub fn iter(&self) -> RBURefIter<'_, T, S> {
+        self.range(0, self.size)
+    }
+
+    fn range(&self, index: usize, end: usize) -> RBURefIter<'_, T, S> {
+        RBURefIter {
+            buffer: self,
+            index,
+            end,
+        }
+    }
+
+    /// Iterates over the newest `n` elements, oldest of them first.
+    pub fn latest(&self, n: usize) -> RBURefIter<'_, T, S> {
+        self.range(self.size.saturating_sub(n), self.size)
+    }
+
+    /// Iterates over the oldest `n` elements, oldest first.
+    pub fn oldest(&self, n: usize) -> RBURefIter<'_, T, S> {
+        self.range(0, n.min(self.size))
+    }
+
+    /// Iterates over consecutive runs of `chunk_size` elements, each yielded as
+    /// an iterator; the last run is shorter if the length does not divide evenly.
+    pub fn chunks(&self, chunk_size: usize) -> RBUChunks<'_, T, S> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        RBUChunks {
+            buffer: self,
+            index: 0,
+            chunk_size,
+        }
+    }
+
+    /// Iterates over every `step`-th element, starting with the oldest.
+    pub fn iter_step(&self, step: usize) -> StepBy<RBURefIter<'_, T, S>> {
+        self.iter().step_by(step)
+    }
+
+    /// Reduces each run of `bucket` consecutive elements to one value with `f`.
+    pub fn downsample<B, F>(&self, bucket: usize, f: F) -> Map<RBUChunks<'_, T, S>, F>
+    where
+        F: FnMut(RBURefIter<'_, T, S>) -> B,
+    {
+        self.chunks(bucket).map(f)
+    }
+
+    pub fn iter_mut(&mut self) -> RBUMutIter<'_, T> {
+        let (head, wrapped) = self.as_mut_slices();
+        RBUMutIter(head.iter_mut().chain(wrapped.iter_mut()))
+    }
+
+    fn slot_ranges(&self) -> (Range<usize>, Range<usize>) {
+        if self.is_empty() {
+            return (0..0, 0..0);
+        }
+        let start = self.index_of(0);
+        let head_len = self.size.min(self.capacity() - start);
+        (start..start + head_len, 0..self.size - head_len)
+    }
+
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (head, wrapped) = self.slot_ranges();
+        unsafe {
+            (
+                slice_assume_init_ref(&self.buffer.as_slice()[head]),
+                slice_assume_init_ref(&self.buffer.as_slice()[wrapped]),
+            )
+        }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (head, wrapped) = self.slot_ranges();
+        let (left, right) = self.buffer.as_mut_slice().split_at_mut(head.start);
+        unsafe {
+            (
+                slice_assume_init_mut(&mut right[..head.len()]),
+                slice_assume_init_mut(&mut left[wrapped]),
+            )
+        }
+    }
+
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if !self.is_empty() {
+            let start = self.index_of(0);
+            self.buffer.as_mut_slice().rotate_left(start);
+            self.start = 0;
+        }
+        self.as_mut_slices().0
+    }
+
+    pub fn drain(&mut self) -> RBUDrain<'_, T, S> {
+        RBUDrain(self)
+    }
+
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+        self.start = 0;
+    }
+
+    /// Replaces the contents with clones of `value`, as many as `fill_with`
+    /// would produce.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.fill_with(|| value.clone());
+    }
+
+    /// Replaces the contents with `capacity()` elements produced by `f`.
+    /// Zero-sized types have a capacity of `usize::MAX`, so they are only
+    /// filled with `ZST_FILL_LIMIT` elements.
+    pub fn fill_with<F: FnMut() -> T>(&mut self, mut f: F) {
+        self.clear();
+        let n = if size_of::<T>() == 0 {
+            ZST_FILL_LIMIT
+        } else {
+            self.capacity()
+        };
+        for _ in 0..n {
+            self.push(f());
+        }
+    }
+
+    /// Copies the contents into an immutable `Snapshot`.
+    pub fn snapshot(&self) -> Snapshot<T>
+    where
+        T: Clone,
+    {
+        Snapshot {
+            elements: self.iter().cloned().collect(),
+            seq: self.seq,
+        }
+    }
+
+    /// Replaces the contents with those of `snapshot`. If it holds more
+    /// elements than fit, only the newest ones are kept.
+    pub fn restore(&mut self, snapshot: &Snapshot<T>)
+    where
+        T: Clone,
+    {
+        self.clear();
+        self.seq = snapshot.seq;
+        self.extend(snapshot.iter().cloned());
+    }
+
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        for _ in 0..self.size {
+            let element = self.pop().unwrap();
+            if f(&element) {
+                self.push(element); // Never overwrites: pop freed a slot
+                self.uncount_pushed(1);
+            }
+        }
+    }
+
+    /// Keeps the newest `len` elements, dropping the oldest ones.
+    pub fn truncate_front(&mut self, len: usize) {
+        while self.size > len {
+            self.pop();
+        }
+    }
+
+    /// Keeps the oldest `len` elements, dropping the newest ones.
+    pub fn truncate_back(&mut self, len: usize) {
+        while self.size > len {
+            self.pop_back();
+        }
+    }
+
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: PartialEq,
+        F: FnMut(&mut T) -> K,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same_bucket(element, previous)` is true.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let len = self.size;
+        if len == 0 {
+            return;
+        }
+        let first = self.pop().unwrap();
+        self.push(first);
+        self.uncount_pushed(1);
+        for _ in 1..len {
+            let mut element = self.pop().unwrap();
+            if !same_bucket(&mut element, self.back_mut().unwrap()) {
+                self.push(element);
+                self.uncount_pushed(1);
+            }
+        }
+    }
+
+    pub fn contains(&self, element: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|x| x == element)
+    }
+
+    pub fn position<F: FnMut(&T) -> bool>(&self, f: F) -> Option<usize> {
+        self.iter().position(f)
+    }
+
+    /// Pushes every element of `other` in order, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        self.extend(other.drain());
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Moves the `n` front elements to `dst` with at most two copies.
+    unsafe fn pop_to_ptr(&mut self, dst: *mut T, n: usize) {
+        debug_assert!(n <= self.size);
+        if n == 0 {
+            return;
+        }
+        let (head, wrapped) = self.slot_ranges();
+        let from_head = n.min(head.len());
+        let src = self.buffer.as_slice().as_ptr() as *const T;
+        ptr::copy_nonoverlapping(src.add(head.start), dst, from_head);
+        ptr::copy_nonoverlapping(src.add(wrapped.start), dst.add(from_head), n - from_head);
+        self.start = self.index_of(n);
+        self.size -= n;
+        self.seq = self.seq.wrapping_add(n);
+    }
+
+    /// Pops up to `n` elements from the front, oldest first.
+    pub fn pop_many(&mut self, n: usize) -> Vec<T> {
+        let n = n.min(self.size);
+        let mut popped = Vec::with_capacity(n);
+        unsafe {
+            self.pop_to_ptr(popped.as_mut_ptr(), n);
+            popped.set_len(n);
+        }
+        popped
+    }
+
+    /// Pops elements from the front into `dst` until either runs out,
+    /// returning how many were written.
+    pub fn pop_into(&mut self, dst: &mut [T]) -> usize {
+        let n = dst.len().min(self.size);
+        for slot in &mut dst[..n] {
+            *slot = self.pop().unwrap();
+        }
+        n
+    }
+
+    /// Like `pop_into`, but for uninitialized destination memory; the
+    /// first returned-count slots of `dst` are initialized afterwards.
+    pub fn pop_into_uninit(&mut self, dst: &mut [MaybeUninit<T>]) -> usize {
+        let n = dst.len().min(self.size);
+        unsafe { self.pop_to_ptr(dst.as_mut_ptr() as *mut T, n) };
+        n
+    }
+
+    /// Pops the front element only if `predicate` holds for it.
+    pub fn pop_if<F: FnOnce(&T) -> bool>(&mut self, predicate: F) -> Option<T> {
+        if predicate(self.front()?) {
+            self.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the newest element if it had to be overwritten.
+    pub fn push_front(&mut self, element: T) -> Option<T> {
+        if self.capacity() == 0 {
+            self.count_overwritten(1);
+            self.count_pushed(1);
+            return Some(element);
+        }
+        let evicted = if self.is_full() {
+            self.count_overwritten(1);
+            self.pop_back()
+        } else {
+            None
+        };
+        let idx = match self.index_of(0) {
+            0 => self.capacity() - 1,
+            idx => idx - 1,
+        };
+        self.buffer.as_mut_slice()[idx] = MaybeUninit::new(element);
+        self.start = idx;
+        self.size += 1;
+        self.seq = self.seq.wrapping_sub(1);
+        self.count_pushed(1);
+        evicted
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        let idx = self.index_of(self.size - 1);
         self.size -= 1;
         Some(unsafe {
-            replace(self.buffer.get_mut(idx).unwrap(), MaybeUninit::uninit()).assume_init()
+            replace(
+                self.buffer.as_mut_slice().get_mut(idx).unwrap(),
+                MaybeUninit::uninit(),
+            )
+            .assume_init()
+        })
+    }
+
+    pub fn swap(&mut self, i: usize, j: usize) {
+        assert!(i < self.size && j < self.size, "index out of bounds");
+        let (a, b) = (self.index_of(i), self.index_of(j));
+        self.buffer.as_mut_slice().swap(a, b);
+    }
+
+    /// Removes the element at `index`, replacing it with the front element.
+    pub fn swap_remove_front(&mut self, index: usize) -> Option<T> {
+        if index >= self.size {
+            return None;
+        }
+        self.swap(0, index);
+        self.pop()
+    }
+
+    /// Removes the element at `index`, replacing it with the back element.
+    pub fn swap_remove_back(&mut self, index: usize) -> Option<T> {
+        if index >= self.size {
+            return None;
+        }
+        self.swap(index, self.size - 1);
+        self.pop_back()
+    }
+
+    /// Inserts `element` at logical `index`, shifting the shorter side.
+    /// When full, the oldest element is evicted first, as with `push`,
+    /// so inserting at index 0 of a full buffer returns `element` itself.
+    pub fn insert(&mut self, mut index: usize, element: T) -> Option<T> {
+        assert!(index <= self.size, "index out of bounds");
+        let evicted = if self.is_full() {
+            self.count_overwritten(1);
+            if index == 0 {
+                self.count_pushed(1);
+                return Some(element);
+            }
+            index -= 1;
+            self.pop()
+        } else {
+            None
+        };
+        if index <= self.size / 2 {
+            self.push_front(element);
+            for k in 0..index {
+                self.swap(k, k + 1);
+            }
+        } else {
+            self.push(element);
+            for k in (index..self.size - 1).rev() {
+                self.swap(k, k + 1);
+            }
+        }
+        evicted
+    }
+
+    /// Removes the element at logical `index`, shifting the shorter side.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.size {
+            return None;
+        }
+        if index <= self.size / 2 {
+            for k in (0..index).rev() {
+                self.swap(k, k + 1);
+            }
+            self.pop()
+        } else {
+            for k in index..self.size - 1 {
+                self.swap(k, k + 1);
+            }
+            self.pop_back()
+        }
+    }
+
+    fn reverse(&mut self, mut from: usize, mut to: usize) {
+        while from + 1 < to {
+            to -= 1;
+            self.swap(from, to);
+            from += 1;
+        }
+    }
+
+    /// Rotates the logical contents so that the element at `n` becomes the front.
+    pub fn rotate_left(&mut self, n: usize) {
+        assert!(n <= self.size, "rotation out of bounds");
+        if self.is_full() {
+            self.start = self.index_of(n); // Every slot is live, only the front moves
+        } else {
+            self.reverse(0, n);
+            self.reverse(n, self.size);
+            self.reverse(0, self.size);
+        }
+    }
+
+    /// Rotates the logical contents so that the last `n` elements move to the front.
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(n <= self.size, "rotation out of bounds");
+        self.rotate_left(self.size - n);
+    }
+
+    pub fn binary_search(&self, element: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        self.binary_search_by(|x| x.cmp(element))
+    }
+
+    pub fn binary_search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        let (mut low, mut high) = (0, self.size);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self[mid]) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    pub fn binary_search_by_key<B, F>(&self, key: &B, mut f: F) -> Result<usize, usize>
+    where
+        B: Ord,
+        F: FnMut(&T) -> B,
+    {
+        self.binary_search_by(|x| f(x).cmp(key))
+    }
+
+    /// Returns the index of the first element for which `pred` is false,
+    /// assuming the buffer is partitioned by it.
+    pub fn partition_point<F>(&self, mut pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.binary_search_by(|x| {
+            if pred(x) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
         })
+        .unwrap_or_else(|idx| idx)
+    }
+
+    /// Inserts `element` after all elements that are not greater than it,
+    /// keeping a sorted buffer sorted. When full, the element at `evict` is
+    /// evicted, so an ascending window drops its minimum with `End::Front`
+    /// and its maximum with `End::Back`. An element that would itself be
+    /// the one evicted is returned without being inserted.
+    pub fn insert_sorted(&mut self, element: T, evict: End) -> Option<T>
+    where
+        T: Ord,
+    {
+        self.insert_sorted_by(element, evict, T::cmp)
+    }
+
+    pub fn insert_sorted_by<F>(&mut self, element: T, evict: End, mut f: F) -> Option<T>
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let idx = self.partition_point(|x| f(x, &element) != Ordering::Greater);
+        self.insert_evicting(idx, element, evict)
+    }
+
+    pub fn insert_sorted_by_key<K, F>(&mut self, element: T, evict: End, mut f: F) -> Option<T>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        let key = f(&element);
+        let idx = self.partition_point(|x| f(x) <= key);
+        self.insert_evicting(idx, element, evict)
+    }
+
+    fn insert_evicting(&mut self, index: usize, element: T, evict: End) -> Option<T> {
+        if evict == End::Front || !self.is_full() {
+            return self.insert(index, element);
+        }
+        self.count_overwritten(1);
+        if index == self.size {
+            self.count_pushed(1);
+            return Some(element);
+        }
+        let evicted = self.pop_back();
+        self.insert(index, element);
+        evicted
+    }
+
+    pub fn cursor_front(&self) -> Cursor {
+        Cursor::new(self.seq)
+    }
+
+    /// Points just past the back: at the element the next `push` will add.
+    pub fn cursor_end(&self) -> Cursor {
+        Cursor::new(self.seq.wrapping_add(self.size))
+    }
+
+    pub fn cursor_index(&self, cursor: Cursor) -> Result<usize, CursorError> {
+        cursor.index(self.seq, self.size)
+    }
+
+    pub fn cursor_get(&self, cursor: Cursor) -> Result<&T, CursorError> {
+        let index = self.cursor_index(cursor)?;
+        Ok(&self[index])
+    }
+
+    pub fn cursor_get_mut(&mut self, cursor: Cursor) -> Result<&mut T, CursorError> {
+        let index = self.cursor_index(cursor)?;
+        Ok(&mut self[index])
+    }
+
+    pub fn sort(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_by(T::cmp);
+    }
+
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    pub fn sort_unstable(&mut self)
+    where
+        T: Ord,
+    {
+        self.sort_unstable_by(T::cmp);
+    }
+
+    pub fn sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.make_contiguous().sort_by(compare);
+    }
+
+    pub fn sort_unstable_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        self.make_contiguous().sort_unstable_by(compare);
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.size.checked_sub(1)?)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(self.size.checked_sub(1)?)
+    }
+}
+
+impl<T, S: Storage<T>> Index<usize> for RingBufferU<T, S> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("Out of bounds access")
+    }
+}
+
+impl<T, S: Storage<T>> IndexMut<usize> for RingBufferU<T, S> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("Out of bounds access")
+    }
+}
+
+impl<T: Clone> Clone for RingBufferU<T> {
+    fn clone(&self) -> Self {
+        let mut rb = Self::with_capacity(self.capacity());
+        rb.seq = self.seq;
+        rb.overwritten = self.overwritten;
+        rb.overwritten_reset = self.overwritten_reset;
+        rb.extend(self.iter().cloned());
+        rb.copy_stats(self);
+        rb
+    }
+}
+
+impl<T: fmt::Debug, S: Storage<T>> fmt::Debug for RingBufferU<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 
-impl<T> IntoIterator for RingBufferU<T> {
+/// Buffers are equal when they hold the same elements in the same logical order;
+/// capacity and the position of the oldest element in storage are not compared.
+impl<T: PartialEq, S: Storage<T>> PartialEq for RingBufferU<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, S: Storage<T>> Eq for RingBufferU<T, S> {}
+
+impl<T: PartialOrd, S: Storage<T>> PartialOrd for RingBufferU<T, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, S: Storage<T>> Ord for RingBufferU<T, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash, S: Storage<T>> Hash for RingBufferU<T, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.size.hash(state);
+        self.iter().for_each(|element| element.hash(state));
+    }
+}
+
+impl<T, S: Storage<T>> Extend<T> for RingBufferU<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+/// The resulting buffer is full: its capacity is the length of the vector.
+impl<T> From<Vec<T>> for RingBufferU<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let mut rb = Self::with_capacity(vec.len());
+        rb.extend(vec);
+        rb
+    }
+}
+
+/// The resulting buffer is full: its capacity is the length of the deque.
+impl<T> From<VecDeque<T>> for RingBufferU<T> {
+    fn from(deque: VecDeque<T>) -> Self {
+        let mut rb = Self::with_capacity(deque.len());
+        rb.extend(deque);
+        rb
+    }
+}
+
+impl<T, S: Storage<T>> From<RingBufferU<T, S>> for VecDeque<T> {
+    fn from(rb: RingBufferU<T, S>) -> Self {
+        rb.into_iter().collect()
+    }
+}
+
+/// The resulting buffer is full: its capacity is the number of collected items.
+impl<T> FromIterator<T> for RingBufferU<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let elements = Vec::from_iter(iter);
+        let mut rb = Self::with_capacity(elements.len());
+        rb.extend(elements);
+        rb
+    }
+}
+
+impl<T, S: Storage<T>> IntoIterator for RingBufferU<T, S> {
     type Item = T;
-    type IntoIter = RBUIter<T>;
+    type IntoIter = RBUIter<T, S>;
 
     fn into_iter(self) -> Self::IntoIter {
         RBUIter(self)
     }
 }
 
-impl<T> Drop for RingBufferU<T> {
+impl<T, S: Storage<T>> Drop for RingBufferU<T, S> {
     fn drop(&mut self) {
-        while self.size > 0 {
-            self.pop();
+        self.clear();
+    }
+}
+
+pub struct RBUIter<T, S: Storage<T> = Vec<MaybeUninit<T>>>(RingBufferU<T, S>);
+
+impl<T, S: Storage<T>> Iterator for RBUIter<T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.size == 0 {
+            return None;
+        }
+        let idx = self.0.index_of(0);
+        self.0.start = self.0.index_of(1);
+        self.0.size -= 1;
+        Some(unsafe {
+            replace(
+                self.0.buffer.as_mut_slice().get_mut(idx).unwrap(),
+                MaybeUninit::uninit(),
+            )
+            .assume_init()
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.size, Some(self.0.size))
+    }
+}
+
+impl<T, S: Storage<T>> DoubleEndedIterator for RBUIter<T, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T, S: Storage<T>> ExactSizeIterator for RBUIter<T, S> {}
+
+impl<T, S: Storage<T>> FusedIterator for RBUIter<T, S> {}
+
+pub struct RBURefIter<'a, T, S: Storage<T> = Vec<MaybeUninit<T>>> {
+    buffer: &'a RingBufferU<T, S>,
+    index: usize,
+    end: usize,
+}
+
+impl<'a, T, S: Storage<T>> Iterator for RBURefIter<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.end {
+            return None;
+        }
+        let element = self.buffer.get(self.index)?;
+        self.index += 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T, S: Storage<T>> ExactSizeIterator for RBURefIter<'a, T, S> {}
+
+impl<'a, T, S: Storage<T>> Clone for RBURefIter<'a, T, S> {
+    fn clone(&self) -> Self {
+        RBURefIter {
+            buffer: self.buffer,
+            index: self.index,
+            end: self.end,
+        }
+    }
+}
+
+pub struct RBUChunks<'a, T, S: Storage<T> = Vec<MaybeUninit<T>>> {
+    buffer: &'a RingBufferU<T, S>,
+    index: usize,
+    chunk_size: usize,
+}
+
+impl<'a, T, S: Storage<T>> Iterator for RBUChunks<'a, T, S> {
+    type Item = RBURefIter<'a, T, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.buffer.size {
+            return None;
         }
+        let end = self.buffer.size.min(self.index + self.chunk_size);
+        let chunk = self.buffer.range(self.index, end);
+        self.index = end;
+        Some(chunk)
+    }
+}
+
+impl<'a, T, S: Storage<T>> IntoIterator for &'a RingBufferU<T, S> {
+    type Item = &'a T;
+    type IntoIter = RBURefIter<'a, T, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct RBUMutIter<'a, T>(Chain<IterMut<'a, T>, IterMut<'a, T>>);
+
+impl<'a, T> Iterator for RBUMutIter<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, T, S: Storage<T>> IntoIterator for &'a mut RingBufferU<T, S> {
+    type Item = &'a mut T;
+    type IntoIter = RBUMutIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct RBUDrain<'a, T, S: Storage<T> = Vec<MaybeUninit<T>>>(&'a mut RingBufferU<T, S>);
+
+impl<'a, T, S: Storage<T>> Iterator for RBUDrain<'a, T, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.size, Some(self.0.size))
     }
 }
 
-pub struct RBUIter<T>(RingBufferU<T>);
+impl<'a, T, S: Storage<T>> ExactSizeIterator for RBUDrain<'a, T, S> {}
 
-impl<T> Iterator for RBUIter<T> {
-    type Item = T;
+impl<'a, T, S: Storage<T>> FusedIterator for RBUDrain<'a, T, S> {}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.0.size == 0 {
-            return None;
-        }
-        let idx = self.0.start;
-        self.0.start = (self.0.start + 1) % self.0.capacity();
-        self.0.size -= 1;
-        Some(unsafe {
-            replace(self.0.buffer.get_mut(idx).unwrap(), MaybeUninit::uninit()).assume_init()
-        })
+impl<'a, T, S: Storage<T>> Drop for RBUDrain<'a, T, S> {
+    fn drop(&mut self) {
+        self.for_each(drop);
     }
 }
 
@@ -119,7 +1291,7 @@ mod tests {
         use std::iter::FromIterator;
         let mut rb = RingBufferU::with_capacity(7);
         for i in 0..7 {
-            rb.push(i)
+            rb.push(i);
         }
         assert_eq!(rb.pop(), Some(0));
         assert_eq!(rb.pop(), Some(1));
@@ -130,13 +1302,842 @@ mod tests {
     #[test]
     fn zero_sized_types_test() {
         use std::iter::FromIterator;
-        struct ZST;
+        struct Zst;
         let mut rb = RingBufferU::with_capacity(3);
-        rb.push(ZST {});
-        rb.push(ZST {});
-        rb.push(ZST {});
+        rb.push(Zst {});
+        rb.push(Zst {});
+        rb.push(Zst {});
         rb.pop();
         assert_eq!(rb.capacity(), usize::MAX);
         assert_eq!(Vec::from_iter(rb.into_iter()).len(), 2);
     }
+
+    #[test]
+    fn front_back_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        assert_eq!(rb.front(), None);
+        assert_eq!(rb.back(), None);
+        rb.push(1);
+        assert_eq!(rb.front(), Some(&1));
+        assert_eq!(rb.back(), Some(&1));
+        rb.push(2);
+        rb.push(3);
+        rb.push(4);
+        assert_eq!(rb.front(), Some(&2));
+        assert_eq!(rb.back(), Some(&4));
+        *rb.front_mut().unwrap() = 20;
+        *rb.back_mut().unwrap() = 40;
+        assert_eq!(rb.pop(), Some(20));
+        assert_eq!(rb.pop(), Some(3));
+        assert_eq!(rb.pop(), Some(40));
+    }
+
+    #[test]
+    fn len_test() {
+        let mut rb = RingBufferU::with_capacity(2);
+        assert_eq!(rb.len(), 0);
+        assert!(rb.is_empty());
+        assert!(!rb.is_full());
+        rb.push(1);
+        assert_eq!(rb.len(), 1);
+        assert!(!rb.is_empty());
+        assert!(!rb.is_full());
+        rb.push(2);
+        rb.push(3);
+        assert_eq!(rb.len(), 2);
+        assert!(rb.is_full());
+        rb.pop();
+        assert_eq!(rb.len(), 1);
+        assert!(!rb.is_full());
+    }
+
+    #[test]
+    fn get_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        assert_eq!(rb.get(0), None);
+        for i in 0..5 {
+            rb.push(i);
+        }
+        assert_eq!(rb.get(0), Some(&2));
+        assert_eq!(rb.get(1), Some(&3));
+        assert_eq!(rb.get(2), Some(&4));
+        assert_eq!(rb.get(3), None);
+        *rb.get_mut(1).unwrap() = 30;
+        assert_eq!(rb.get(1), Some(&30));
+        assert_eq!(rb.get_mut(3), None);
+    }
+
+    #[test]
+    fn ref_iter_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        for i in 0..6 {
+            rb.push(i);
+        }
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &5]);
+        rb.push(6);
+        let mut seen = Vec::new();
+        for element in &rb {
+            seen.push(*element);
+        }
+        assert_eq!(seen, vec![3, 4, 5, 6]);
+        assert_eq!(rb.len(), 4);
+    }
+
+    #[test]
+    fn mut_iter_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        for i in 0..6 {
+            rb.push(i);
+        }
+        for element in rb.iter_mut() {
+            *element *= 10;
+        }
+        for element in &mut rb {
+            *element += 1;
+        }
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&21, &31, &41, &51]);
+        assert_eq!(RingBufferU::<i32>::with_capacity(3).iter_mut().next(), None);
+    }
+
+    #[test]
+    fn index_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        for i in 0..4 {
+            rb.push(i);
+        }
+        assert_eq!(rb[0], 1);
+        assert_eq!(rb[2], 3);
+        rb[1] = 20;
+        assert_eq!(rb.get(1), Some(&20));
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_out_of_bounds_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.push(1);
+        let _ = rb[1];
+    }
+
+    #[test]
+    fn as_slices_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        assert_eq!(rb.as_slices(), (&[][..], &[][..]));
+        rb.push(0);
+        rb.push(1);
+        assert_eq!(rb.as_slices(), (&[0, 1][..], &[][..]));
+        for i in 2..6 {
+            rb.push(i);
+        }
+        assert_eq!(rb.as_slices(), (&[2, 3][..], &[4, 5][..]));
+        let (head, wrapped) = rb.as_mut_slices();
+        head[0] = 20;
+        wrapped[1] = 50;
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&20, &3, &4, &50]);
+    }
+
+    #[test]
+    fn make_contiguous_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        for i in 0..7 {
+            rb.push(i);
+        }
+        rb.pop();
+        rb.make_contiguous().sort_by(|a, b| b.cmp(a));
+        assert_eq!(rb.as_slices(), (&[6, 5, 4, 3][..], &[][..]));
+        rb.push(7);
+        rb.push(8);
+        assert_eq!(rb.make_contiguous(), &[5, 4, 3, 7, 8]);
+    }
+
+    #[test]
+    fn extend_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.push(0);
+        rb.extend(1..3);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &1, &2]);
+        rb.extend(vec![3, 4]);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4]);
+    }
+
+    #[test]
+    fn from_iter_test() {
+        let mut rb = (0..4).collect::<RingBufferU<_>>();
+        assert_eq!(rb.capacity(), 4);
+        assert!(rb.is_full());
+        rb.push(4);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &3, &4]);
+        assert!(std::iter::empty::<i32>()
+            .collect::<RingBufferU<_>>()
+            .is_empty());
+    }
+
+    #[test]
+    fn rev_iter_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..6);
+        let mut iter = rb.into_iter();
+        assert_eq!(iter.next_back(), Some(5));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.rev().collect::<Vec<_>>(), vec![4, 3]);
+    }
+
+    #[test]
+    fn size_hint_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..6);
+        let mut iter = rb.into_iter();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.len(), 0);
+    }
+
+    #[test]
+    fn drain_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.extend(0..4);
+        assert_eq!(rb.drain().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(rb.is_empty());
+        assert_eq!(rb.capacity(), 3);
+        rb.extend(4..6);
+        let mut drain = rb.drain();
+        assert_eq!(drain.next(), Some(4));
+        drop(drain);
+        assert!(rb.is_empty());
+        rb.push(6);
+        assert_eq!(rb.pop(), Some(6));
+    }
+
+    #[test]
+    fn clear_test() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let mut rb = RingBufferU::with_capacity(3);
+        for _ in 0..4 {
+            rb.push(Rc::clone(&counter));
+        }
+        assert_eq!(Rc::strong_count(&counter), 4);
+        rb.clear();
+        assert_eq!(Rc::strong_count(&counter), 1);
+        assert!(rb.is_empty());
+        assert_eq!(rb.capacity(), 3);
+        rb.push(Rc::clone(&counter));
+        assert_eq!(rb.len(), 1);
+    }
+
+    #[test]
+    fn retain_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(0..8);
+        rb.retain(|&x| x % 2 == 1);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&3, &5, &7]);
+        rb.push(8);
+        rb.push(9);
+        rb.push(10);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&5, &7, &8, &9, &10]);
+    }
+
+    #[test]
+    fn truncate_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(0..7);
+        rb.truncate_front(4);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&3, &4, &5, &6]);
+        rb.truncate_back(2);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&3, &4]);
+        rb.truncate_back(5);
+        assert_eq!(rb.len(), 2);
+        rb.extend(7..10);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&3, &4, &7, &8, &9]);
+    }
+
+    #[test]
+    fn contains_position_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.extend(0..5);
+        assert!(!rb.contains(&1));
+        assert!(rb.contains(&2));
+        assert_eq!(rb.position(|&x| x > 2), Some(1));
+        assert_eq!(rb.position(|&x| x > 4), None);
+    }
+
+    #[test]
+    fn eq_test() {
+        let mut wrapped = RingBufferU::with_capacity(3);
+        wrapped.extend(0..5);
+        let mut straight = RingBufferU::with_capacity(3);
+        straight.extend(2..5);
+        assert!(wrapped == straight);
+        let mut larger = RingBufferU::with_capacity(10);
+        larger.extend(2..5);
+        assert!(wrapped == larger);
+        larger.push(5);
+        assert!(wrapped != larger);
+        straight.pop();
+        straight.push(6);
+        assert!(wrapped != straight);
+    }
+
+    #[test]
+    fn hash_test() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashSet;
+        fn hash_of(rb: &RingBufferU<i32>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            rb.hash(&mut hasher);
+            hasher.finish()
+        }
+        let mut wrapped = RingBufferU::with_capacity(3);
+        wrapped.extend(0..5);
+        let mut straight = RingBufferU::with_capacity(4);
+        straight.extend(2..5);
+        assert_eq!(hash_of(&wrapped), hash_of(&straight));
+        let mut set = HashSet::new();
+        set.insert(wrapped);
+        assert!(set.contains(&straight));
+    }
+
+    #[test]
+    fn ord_test() {
+        let mut a = RingBufferU::with_capacity(3);
+        a.extend(0..5);
+        let b = vec![2, 3].into_iter().collect::<RingBufferU<_>>();
+        let c = vec![2, 4].into_iter().collect::<RingBufferU<_>>();
+        assert!(b < a);
+        assert!(a < c);
+        assert_eq!(a.cmp(&vec![2, 3, 4].into_iter().collect()), Ordering::Equal);
+        let mut sorted = [c, a, b];
+        sorted.sort();
+        let sorted = sorted.iter().map(|rb| rb.len()).collect::<Vec<_>>();
+        assert_eq!(sorted, vec![2, 3, 2]);
+    }
+
+    #[test]
+    fn debug_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        assert_eq!(format!("{:?}", rb), "[]");
+        rb.extend(0..6);
+        rb.pop();
+        assert_eq!(format!("{:?}", rb), "[3, 4, 5]");
+    }
+
+    #[test]
+    fn clone_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend((0..6).map(|i| i.to_string()));
+        rb.pop();
+        let mut copy = rb.clone();
+        assert_eq!(copy, rb);
+        assert_eq!(copy.capacity(), 4);
+        copy.push("6".to_string());
+        copy.push("7".to_string());
+        assert_eq!(format!("{:?}", copy), r#"["4", "5", "6", "7"]"#);
+        assert_eq!(format!("{:?}", rb), r#"["3", "4", "5"]"#);
+    }
+
+    #[test]
+    fn vec_conversion_test() {
+        let mut v = Vec::with_capacity(10);
+        v.extend(0..3);
+        let mut rb = RingBufferU::from(v);
+        assert_eq!(rb.capacity(), 3);
+        assert!(rb.is_full());
+        rb.push(3);
+        assert_eq!(rb.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn vec_deque_conversion_test() {
+        let mut deque = VecDeque::new();
+        deque.extend(1..4);
+        deque.push_front(0);
+        let mut rb = RingBufferU::from(deque);
+        assert_eq!(rb.capacity(), 4);
+        rb.push(4);
+        let deque: VecDeque<_> = rb.into();
+        assert_eq!(deque, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn push_evict_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        assert_eq!(rb.push(1), None);
+        assert_eq!(rb.push(2), None);
+        assert_eq!(rb.push(3), None);
+        assert_eq!(rb.push(4), Some(1));
+        assert_eq!(rb.push(5), Some(2));
+        assert_eq!(rb.pop(), Some(3));
+        assert_eq!(rb.push(6), None);
+    }
+
+    #[test]
+    fn try_push_test() {
+        let mut rb = RingBufferU::with_capacity(2);
+        assert_eq!(rb.try_push(1), Ok(()));
+        assert_eq!(rb.try_push(2), Ok(()));
+        assert_eq!(rb.try_push(3), Err(3));
+        assert_eq!(rb.pop(), Some(1));
+        assert_eq!(rb.try_push(3), Ok(()));
+        assert_eq!(rb.into_vec(), vec![2, 3]);
+    }
+
+    #[test]
+    fn deque_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        assert_eq!(rb.pop_back(), None);
+        assert_eq!(rb.push_front(1), None);
+        assert_eq!(rb.push_front(0), None);
+        rb.push(2);
+        assert_eq!(rb.push_front(-1), Some(2));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&-1, &0, &1]);
+        assert_eq!(rb.pop_back(), Some(1));
+        assert_eq!(rb.pop(), Some(-1));
+        assert_eq!(rb.pop_back(), Some(0));
+        assert_eq!(rb.pop_back(), None);
+    }
+
+    #[test]
+    fn swap_remove_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(0..7);
+        assert_eq!(rb.swap_remove_back(1), Some(3));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &6, &4, &5]);
+        assert_eq!(rb.swap_remove_front(2), Some(4));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&6, &2, &5]);
+        assert_eq!(rb.swap_remove_front(3), None);
+        assert_eq!(rb.swap_remove_back(2), Some(5));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&6, &2]);
+    }
+
+    #[test]
+    fn insert_remove_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(vec![0, 2, 4]);
+        assert_eq!(rb.insert(1, 1), None);
+        assert_eq!(rb.insert(3, 3), None);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &4]);
+        assert_eq!(rb.insert(0, -1), Some(-1));
+        assert_eq!(rb.insert(5, 5), Some(0));
+        assert_eq!(rb.insert(2, 10), Some(1));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &10, &3, &4, &5]);
+        assert_eq!(rb.remove(1), Some(10));
+        assert_eq!(rb.remove(3), Some(5));
+        assert_eq!(rb.remove(3), None);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4]);
+    }
+
+    #[test]
+    fn rotate_test() {
+        let mut rb = RingBufferU::with_capacity(6);
+        rb.extend(0..5);
+        rb.rotate_left(2);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &0, &1]);
+        rb.rotate_right(3);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&4, &0, &1, &2, &3]);
+        rb.push(5);
+        rb.rotate_left(1);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &5, &4]);
+        rb.rotate_right(6);
+        rb.push(6);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &3, &5, &4, &6]);
+    }
+
+    #[test]
+    fn binary_search_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend((0..8).map(|i| i * 10));
+        assert_eq!(rb.binary_search(&50), Ok(2));
+        assert_eq!(rb.binary_search(&55), Err(3));
+        assert_eq!(rb.binary_search(&0), Err(0));
+        assert_eq!(rb.binary_search_by_key(&7, |x| x / 10), Ok(4));
+        assert_eq!(rb.partition_point(|&x| x <= 50), 3);
+        assert_eq!(rb.partition_point(|&x| x < 100), 5);
+    }
+
+    #[test]
+    fn insert_sorted_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        for i in [5, 1, 4, 2] {
+            assert_eq!(rb.insert_sorted(i, End::Front), None);
+        }
+        assert_eq!(rb.insert_sorted(3, End::Front), Some(1));
+        assert_eq!(rb.insert_sorted(0, End::Front), Some(0));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &5]);
+        assert_eq!(rb.insert_sorted(1, End::Back), Some(5));
+        assert_eq!(rb.insert_sorted(9, End::Back), Some(9));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &3, &4]);
+
+        let mut rb = RingBufferU::with_capacity(3);
+        for i in [5, 1, 4, 2] {
+            rb.insert_sorted_by(i, End::Front, |a, b| b.cmp(a));
+        }
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&4, &2, &1]);
+        rb.insert_sorted_by(3, End::Back, |a, b| b.cmp(a));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&4, &3, &2]);
+
+        let mut rb = RingBufferU::with_capacity(3);
+        for pair in [(1, 'a'), (0, 'b'), (1, 'c')] {
+            rb.insert_sorted_by_key(pair, End::Front, |p| p.0);
+        }
+        assert_eq!(rb.into_vec(), vec![(0, 'b'), (1, 'a'), (1, 'c')]);
+    }
+
+    #[test]
+    fn dedup_test() {
+        let mut rb = RingBufferU::with_capacity(8);
+        rb.extend(vec![9, 9, 1, 1, 2, 1, 1, 3, 3, 3]);
+        rb.dedup();
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &1, &3]);
+        rb.dedup_by_key(|x| *x > 1);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &1, &3]);
+        rb.dedup_by_key(|x| *x / 2);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &1, &3]);
+        rb.dedup_by(|a, b| a > b);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &1]);
+    }
+
+    #[test]
+    fn append_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..3);
+        let mut other = RingBufferU::with_capacity(2);
+        other.extend(3..6);
+        rb.append(&mut other);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&1, &2, &4, &5]);
+        assert!(other.is_empty());
+        assert_eq!(other.capacity(), 2);
+    }
+
+    #[test]
+    fn set_capacity_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..6);
+        rb.set_capacity(6);
+        assert_eq!(rb.capacity(), 6);
+        rb.extend(6..8);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &5, &6, &7]);
+        rb.set_capacity(3);
+        assert_eq!(rb.capacity(), 3);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&5, &6, &7]);
+    }
+
+    #[test]
+    fn reserve_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..6);
+        rb.reserve(1);
+        assert_eq!(rb.capacity(), 8);
+        rb.reserve(2);
+        assert_eq!(rb.capacity(), 8);
+        rb.reserve_exact(5);
+        assert_eq!(rb.capacity(), 9);
+        rb.extend(6..11);
+        assert_eq!(rb.len(), 9);
+        assert_eq!(rb.front(), Some(&2));
+    }
+
+    #[test]
+    fn shrink_test() {
+        let mut rb = RingBufferU::with_capacity(10);
+        rb.extend(0..4);
+        rb.shrink_to(6);
+        assert_eq!(rb.capacity(), 6);
+        rb.shrink_to(2);
+        assert_eq!(rb.capacity(), 4);
+        rb.push(4);
+        rb.pop();
+        rb.shrink_to_fit();
+        assert_eq!(rb.capacity(), 3);
+        assert_eq!(rb.into_vec(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn fill_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.push(7);
+        rb.fill(0);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &0, &0]);
+        let mut next = 0;
+        rb.fill_with(|| {
+            next += 1;
+            next
+        });
+        assert_eq!(rb.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn fill_zero_sized_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.push(());
+        rb.fill(());
+        assert_eq!(rb.len(), ZST_FILL_LIMIT);
+        let mut calls = 0;
+        rb.fill_with(|| calls += 1);
+        assert_eq!(calls, ZST_FILL_LIMIT);
+        assert_eq!(rb.len(), ZST_FILL_LIMIT);
+    }
+
+    #[test]
+    fn latest_oldest_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(0..7);
+        assert_eq!(rb.latest(2).collect::<Vec<_>>(), vec![&5, &6]);
+        assert_eq!(rb.oldest(2).collect::<Vec<_>>(), vec![&2, &3]);
+        assert_eq!(rb.latest(9).count(), 5);
+        assert_eq!(rb.oldest(9).count(), 5);
+        assert_eq!(rb.latest(0).next(), None);
+    }
+
+    #[test]
+    fn pop_if_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        assert_eq!(rb.pop_if(|_| true), None);
+        rb.extend(vec![1, 2, 5, 3]);
+        let mut expired = Vec::new();
+        while let Some(x) = rb.pop_if(|&x| x < 4) {
+            expired.push(x);
+        }
+        assert_eq!(expired, vec![1, 2]);
+        assert_eq!(rb.into_vec(), vec![5, 3]);
+    }
+
+    #[test]
+    fn pop_into_uninit_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend((0..6).map(|i| i.to_string()));
+        let mut dst = [
+            MaybeUninit::uninit(),
+            MaybeUninit::uninit(),
+            MaybeUninit::uninit(),
+        ];
+        assert_eq!(rb.pop_into_uninit(&mut dst), 3);
+        let dst = dst
+            .iter_mut()
+            .map(|slot| unsafe { slot.assume_init_read() })
+            .collect::<Vec<_>>();
+        assert_eq!(dst, vec!["2", "3", "4"]);
+        assert_eq!(rb.pop(), Some("5".to_string()));
+        assert_eq!(rb.pop_into_uninit(&mut [MaybeUninit::uninit()]), 0);
+    }
+
+    #[test]
+    fn pop_many_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend((0..7).map(|i| i.to_string()));
+        assert_eq!(rb.pop_many(4), vec!["2", "3", "4", "5"]);
+        assert_eq!(rb.len(), 1);
+        rb.extend((7..10).map(|i| i.to_string()));
+        let mut dst = vec![String::new(); 3];
+        assert_eq!(rb.pop_into(&mut dst), 3);
+        assert_eq!(dst, vec!["6", "7", "8"]);
+        assert_eq!(rb.pop_many(9), vec!["9"]);
+        assert_eq!(rb.pop_into(&mut dst), 0);
+    }
+
+    #[test]
+    fn extend_from_slice_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend_from_slice(&[0, 1, 2]);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &1, &2]);
+        rb.extend_from_slice(&[3, 4, 5, 6]);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &5, &6]);
+        rb.extend_from_slice(&[]);
+        assert_eq!(rb.len(), 5);
+        rb.clear();
+        rb.extend_from_slice(&[7, 8, 9, 10, 11, 12]);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&8, &9, &10, &11, &12]);
+        rb.pop();
+        rb.pop();
+        rb.extend_from_slice(&[13]);
+        assert_eq!(rb.into_vec(), vec![10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn cursor_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.extend(0..2);
+        let mut cursor = rb.cursor_front();
+        cursor.move_next();
+        let end = rb.cursor_end();
+        assert_eq!(rb.cursor_get(cursor), Ok(&1));
+        assert_eq!(rb.cursor_get(end), Err(CursorError::Pending));
+        rb.push(2);
+        rb.push(3);
+        assert_eq!(rb.cursor_index(cursor), Ok(0));
+        assert_eq!(rb.cursor_get(end), Ok(&2));
+        *rb.cursor_get_mut(end).unwrap() = 20;
+        rb.pop();
+        assert_eq!(rb.cursor_get(cursor), Err(CursorError::Overwritten));
+        cursor.move_next();
+        assert_eq!(rb.cursor_get(cursor), Ok(&20));
+        rb.push_front(1);
+        assert_eq!(rb.cursor_get(rb.cursor_front()), Ok(&1));
+        assert_eq!(rb.cursor_get(cursor), Ok(&20));
+    }
+
+    #[test]
+    fn chunks_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(0..7);
+        let chunks = rb
+            .chunks(2)
+            .map(|chunk| chunk.copied().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(chunks, vec![vec![2, 3], vec![4, 5], vec![6]]);
+        assert_eq!(rb.chunks(5).count(), 1);
+        assert_eq!(RingBufferU::<i32>::with_capacity(2).chunks(1).count(), 0);
+    }
+
+    #[test]
+    fn swap_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..6);
+        rb.swap(0, 3);
+        rb.swap(1, 1);
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&5, &3, &4, &2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_out_of_bounds_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..2);
+        rb.swap(0, 2);
+    }
+
+    #[test]
+    fn sort_test() {
+        let mut rb = RingBufferU::with_capacity(5);
+        rb.extend(vec![9, 4, 1, 3, 0, 2, 1]);
+        rb.pop();
+        rb.sort();
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3]);
+        rb.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&3, &2, &1, &0]);
+        rb.sort_by_key(|x| (x % 2, *x));
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![&0, &2, &1, &3]);
+        rb.push(4);
+        rb.push(5);
+        rb.sort_unstable();
+        assert_eq!(rb.into_vec(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn downsample_test() {
+        let mut rb = RingBufferU::with_capacity(8);
+        rb.extend(0..10);
+        assert_eq!(rb.iter_step(3).collect::<Vec<_>>(), vec![&2, &5, &8]);
+        let sums = rb
+            .downsample(3, |bucket| bucket.sum::<i32>())
+            .collect::<Vec<_>>();
+        assert_eq!(sums, vec![9, 18, 17]);
+        let maxima = rb.downsample(4, |bucket| *bucket.max().unwrap());
+        assert_eq!(maxima.collect::<Vec<_>>(), vec![5, 9]);
+    }
+
+    #[test]
+    fn storage_test() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let storage: Box<[MaybeUninit<Rc<()>>]> = (0..3).map(|_| MaybeUninit::uninit()).collect();
+        let mut rb = RingBufferU::from_storage(storage);
+        assert_eq!(rb.capacity(), 3);
+        rb.extend((0..5).map(|_| Rc::clone(&counter)));
+        assert_eq!(Rc::strong_count(&counter), 4);
+        drop(rb);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn pow2_test() {
+        let mut rb = RingBufferU::with_capacity_pow2(5);
+        assert_eq!(rb.capacity(), 8);
+        rb.extend(0..20);
+        assert_eq!(rb.pop(), Some(12));
+        rb.push(20);
+        assert_eq!(rb.into_vec(), (13..21).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn long_running_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        for i in 0..1000 {
+            rb.push(i);
+        }
+        assert_eq!(rb.pop(), Some(997));
+        rb.push(1000);
+        let cursor = rb.cursor_front();
+        assert_eq!(rb.cursor_get(cursor), Ok(&998));
+        assert_eq!(rb.into_iter().collect::<Vec<_>>(), vec![998, 999, 1000]);
+    }
+
+    #[test]
+    fn zero_capacity_test() {
+        let mut rb = RingBufferU::with_capacity(0);
+        assert_eq!(rb.push(1), Some(1));
+        assert_eq!(rb.push_front(2), Some(2));
+        assert_eq!(rb.insert(0, 3), Some(3));
+        assert_eq!(rb.try_push(4), Err(4));
+        rb.extend_from_slice(&[5, 6]);
+        rb.rotate_left(0);
+        assert_eq!(rb.pop(), None);
+        assert_eq!(rb.pop_back(), None);
+        assert!(rb.is_empty() && rb.is_full());
+        assert_eq!(rb.into_vec(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn overwritten_test() {
+        let mut rb = RingBufferU::with_capacity(3);
+        rb.extend(0..5);
+        assert_eq!(rb.overwritten(), 2);
+        assert_eq!(rb.try_push(5), Err(5));
+        rb.push_front(6);
+        rb.insert(1, 7);
+        rb.extend_from_slice(&[8, 9]);
+        assert_eq!(rb.overwritten(), 6);
+        assert_eq!(rb.reset_overwritten(), 6);
+        rb.pop();
+        rb.push(10);
+        assert_eq!(rb.overwritten_since_reset(), 0);
+        rb.set_capacity(1);
+        assert_eq!(rb.clone().overwritten_since_reset(), 2);
+        assert_eq!(rb.overwritten(), 8);
+    }
+
+    #[cfg(feature = "op-stats")]
+    #[test]
+    fn stats_test() {
+        let mut rb = RingBufferU::with_capacity(4);
+        rb.extend(0..3);
+        rb.pop();
+        rb.extend_from_slice(&[3, 4, 5]);
+        rb.retain(|&x| x != 3);
+        rb.dedup();
+        let stats = rb.stats();
+        assert_eq!(stats.pushed, 6);
+        assert_eq!(stats.popped, 2);
+        assert_eq!(stats.overwritten, 1);
+        assert_eq!(stats.len, 3);
+        assert_eq!(stats.peak_len, 4);
+        rb.set_capacity(2);
+        rb.insert(0, 6);
+        assert_eq!(rb.clone().stats().overwritten, 3);
+        assert_eq!(rb.stats().pushed, 7);
+        assert_eq!(rb.stats().popped, 2);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn allocator_test() {
+        use allocator_api2::alloc::Global;
+        let mut rb = RingBufferU::with_capacity_in(2, Global);
+        rb.extend(0..5);
+        assert_eq!(rb.capacity(), 2);
+        assert_eq!(rb.into_vec(), vec![3, 4]);
+    }
 }
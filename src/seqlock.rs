@@ -0,0 +1,269 @@
+//! A single-writer ring buffer that readers copy whole, seqlock-style.
+//!
+//! Readers do not consume anything: they take a consistent snapshot of the
+//! current window, say for telemetry, while the `Writer` keeps overwriting
+//! the oldest elements. The writer never waits for readers. It bumps a
+//! sequence counter to odd before it touches the buffer and back to even
+//! after; a reader copies the window, and retries if the counter was odd or
+//! changed in the meantime. Readers can therefore starve under a writer that
+//! never pauses, which is the price of never blocking it.
+//!
+//! A reader's copy may overlap a write, so elements are stored as atomic
+//! words and copied with relaxed loads and stores. That is why elements must
+//! be `Plain`: a torn copy is thrown away without being looked at, but every
+//! byte written has to be initialized.
+
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+use crate::sync::{fence, spin_loop, Arc, AtomicU64, Ordering};
+
+/// Element types the seqlock can copy word by word.
+///
+/// # Safety
+///
+/// The type must have no padding or other uninitialized bytes.
+pub unsafe trait Plain: Copy {}
+
+macro_rules! plain {
+    ($($t:ty),*) => {
+        $(unsafe impl Plain for $t {})*
+    };
+}
+
+plain!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+const WORD: usize = mem::size_of::<u64>();
+
+struct Shared<T> {
+    seq: AtomicU64,    // Odd while the writer is modifying the buffer
+    pushed: AtomicU64, // Elements ever pushed; the newest `capacity` are kept
+    capacity: usize,
+    words: Box<[AtomicU64]>, // `capacity` slots of `words_per::<T>()` words
+    marker: PhantomData<T>,
+}
+
+fn words_per<T>() -> usize {
+    mem::size_of::<T>().div_ceil(WORD)
+}
+
+impl<T: Plain> Shared<T> {
+    fn slot(&self, seq: u64) -> &[AtomicU64] {
+        let n = words_per::<T>();
+        let start = (seq % self.capacity as u64) as usize * n;
+        &self.words[start..start + n]
+    }
+
+    fn store(&self, seq: u64, element: T) {
+        let src = &element as *const T as *const u8;
+        for (i, word) in self.slot(seq).iter().enumerate() {
+            let len = WORD.min(mem::size_of::<T>() - i * WORD);
+            let mut bytes = [0; WORD];
+            unsafe { ptr::copy_nonoverlapping(src.add(i * WORD), bytes.as_mut_ptr(), len) };
+            word.store(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+        }
+    }
+
+    /// A possibly torn copy, only to be trusted once the sequence counter
+    /// vouches for it.
+    fn load(&self, seq: u64, copy: &mut MaybeUninit<T>) {
+        let dst = copy.as_mut_ptr() as *mut u8;
+        for (i, word) in self.slot(seq).iter().enumerate() {
+            let len = WORD.min(mem::size_of::<T>() - i * WORD);
+            let bytes = word.load(Ordering::Relaxed).to_ne_bytes();
+            unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), dst.add(i * WORD), len) };
+        }
+    }
+}
+
+pub fn with_capacity<T: Plain>(cap: usize) -> (Writer<T>, Reader<T>) {
+    let shared = Arc::new(Shared {
+        seq: AtomicU64::new(0),
+        pushed: AtomicU64::new(0),
+        capacity: cap,
+        words: (0..cap * words_per::<T>())
+            .map(|_| AtomicU64::new(0))
+            .collect(),
+        marker: PhantomData,
+    });
+    let reader = Reader {
+        shared: Arc::clone(&shared),
+    };
+    (Writer { shared }, reader)
+}
+
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Plain> Writer<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        let pushed = self.shared.pushed.load(Ordering::Relaxed);
+        pushed.min(self.capacity() as u64) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Overwrites the oldest element once the buffer is full.
+    pub fn push(&mut self, element: T) {
+        self.extend_from_slice(&[element]);
+    }
+
+    /// Pushes every element of `src` in one write, so a snapshot contains
+    /// either all of them or none.
+    pub fn extend_from_slice(&mut self, src: &[T]) {
+        let cap = self.capacity() as u64;
+        if cap == 0 || src.is_empty() {
+            return;
+        }
+        let shared = &*self.shared;
+        let seq = shared.seq.load(Ordering::Relaxed);
+        shared.seq.store(seq + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        let pushed = shared.pushed.load(Ordering::Relaxed);
+        let skip = src.len().saturating_sub(cap as usize); // Would be overwritten anyway
+        for (i, &element) in src[skip..].iter().enumerate() {
+            shared.store(pushed + (skip + i) as u64, element);
+        }
+        shared
+            .pushed
+            .store(pushed + src.len() as u64, Ordering::Relaxed);
+        shared.seq.store(seq + 2, Ordering::Release);
+    }
+
+    pub fn reader(&self) -> Reader<T> {
+        Reader {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Reader<T> {
+    fn clone(&self) -> Self {
+        Reader {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T: Plain> Reader<T> {
+    /// Number of elements ever pushed, including overwritten ones.
+    pub fn pushed(&self) -> u64 {
+        self.shared.pushed.load(Ordering::Acquire)
+    }
+
+    /// Copies the current window, oldest element first.
+    pub fn snapshot(&self) -> Vec<T> {
+        let mut window = Vec::new();
+        self.snapshot_into(&mut window);
+        window
+    }
+
+    /// Like `snapshot`, but reuses the allocation of `window`.
+    pub fn snapshot_into(&self, window: &mut Vec<T>) {
+        let shared = &*self.shared;
+        let cap = shared.capacity;
+        window.clear();
+        window.reserve(cap);
+        loop {
+            let seq = shared.seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                spin_loop();
+                continue;
+            }
+            let pushed = shared.pushed.load(Ordering::Relaxed);
+            let len = pushed.min(cap as u64);
+            let copies = &mut window.spare_capacity_mut()[..len as usize];
+            for (i, copy) in copies.iter_mut().enumerate() {
+                shared.load(pushed - len + i as u64, copy);
+            }
+            fence(Ordering::Acquire);
+            if shared.seq.load(Ordering::Relaxed) == seq {
+                // No write overlapped the copies, so they are whole elements
+                unsafe { window.set_len(len as usize) };
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn snapshot_test() {
+        let (mut writer, reader) = with_capacity::<u32>(3);
+        assert!(reader.snapshot().is_empty());
+        writer.push(1);
+        writer.push(2);
+        assert_eq!(reader.snapshot(), [1, 2]);
+        writer.extend_from_slice(&[3, 4, 5, 6, 7]);
+        assert_eq!(writer.len(), 3);
+        assert_eq!(reader.pushed(), 7);
+        let mut window = vec![0; 10];
+        writer.reader().snapshot_into(&mut window);
+        assert_eq!(window, [5, 6, 7]);
+    }
+
+    #[test]
+    fn threads_test() {
+        let (mut writer, reader) = with_capacity::<[u64; 4]>(8);
+        let readers = (0..3)
+            .map(|_| {
+                let reader = reader.clone();
+                thread::spawn(move || {
+                    let mut window = Vec::new();
+                    while reader.pushed() < 10_000 {
+                        reader.snapshot_into(&mut window);
+                        // Whole elements, consecutive and in order
+                        for pair in window.windows(2) {
+                            assert_eq!(pair[1][0], pair[0][0] + 1);
+                        }
+                        assert!(window.iter().all(|e| e.iter().all(|&x| x == e[0])));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for i in 0..10_000 {
+            writer.push([i; 4]);
+        }
+        readers.into_iter().for_each(|r| r.join().unwrap());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn snapshot_test() {
+        // Unbounded, the copy loop has millions of interleavings
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(2);
+        builder.check(|| {
+            let (mut writer, reader) = with_capacity::<[u64; 2]>(1);
+            let handle = thread::spawn(move || reader.snapshot());
+            writer.push([1, 1]);
+            writer.push([2, 2]);
+            // Two words per element, so a torn copy would mix two pushes
+            let window = handle.join().unwrap();
+            assert!(window.iter().all(|e| e[0] == e[1]));
+        });
+    }
+}
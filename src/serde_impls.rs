@@ -0,0 +1,96 @@
+//! Ring buffers serialize as their capacity and their elements in logical
+//! order, oldest first.
+
+use crate::arrayringbuffer::ArrayRingBuffer;
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+struct Elements<'a, T, S: Storage<T>>(&'a RingBufferU<T, S>);
+
+impl<'a, T: Serialize, S: Storage<T>> Serialize for Elements<'a, T, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+impl<T: Serialize, S: Storage<T>> Serialize for RingBufferU<T, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut state = serializer.serialize_struct("RingBuffer", 2)?;
+        state.serialize_field("capacity", &self.capacity())?;
+        state.serialize_field("elements", &Elements(self))?;
+        state.end()
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "RingBuffer")]
+struct Repr<T> {
+    capacity: usize,
+    elements: Vec<T>,
+}
+
+impl<T> Repr<T> {
+    fn check<E: Error>(&self) -> Result<(), E> {
+        if self.elements.len() > self.capacity {
+            return Err(E::custom(format_args!(
+                "{} elements do not fit in capacity {}",
+                self.elements.len(),
+                self.capacity
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for RingBufferU<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Repr::deserialize(deserializer)?;
+        repr.check()?;
+        let mut rb = RingBufferU::with_capacity(repr.capacity);
+        rb.extend(repr.elements);
+        Ok(rb)
+    }
+}
+
+/// Fails unless the serialized capacity is `N`.
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for ArrayRingBuffer<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Repr::deserialize(deserializer)?;
+        repr.check()?;
+        if repr.capacity != N {
+            return Err(D::Error::custom(format_args!(
+                "expected capacity {}, found {}",
+                N, repr.capacity
+            )));
+        }
+        let mut rb = ArrayRingBuffer::new();
+        rb.extend(repr.elements);
+        Ok(rb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn round_trip_test() {
+        let mut rb = RingBuffer::with_capacity(3);
+        rb.extend(1..=4);
+        let json = serde_json::to_string(&rb).unwrap();
+        assert_eq!(json, r#"{"capacity":3,"elements":[2,3,4]}"#);
+        let back: RingBuffer<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, rb);
+        assert_eq!(back.capacity(), 3);
+
+        let array: ArrayRingBuffer<i32, 3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(array.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert!(serde_json::from_str::<ArrayRingBuffer<i32, 4>>(&json).is_err());
+        assert!(
+            serde_json::from_str::<RingBuffer<i32>>(r#"{"capacity":1,"elements":[1,2]}"#).is_err()
+        );
+    }
+}
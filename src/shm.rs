@@ -0,0 +1,427 @@
+//! A single-producer single-consumer ring buffer in shared memory.
+//!
+//! The buffer lives in a memory-mapped file, so two processes that map the
+//! same file can pass elements through it without copying them over a pipe.
+//! One process creates the buffer, the other opens it; each then sticks to
+//! either pushing or popping. The file starts with a fixed header:
+//!
+//! | offset | contents                                      |
+//! |--------|-----------------------------------------------|
+//! | 0      | magic, version, capacity, element size/align  |
+//! | 128    | head: sequence number of the next pop         |
+//! | 136    | pop counter and producers waiting for room    |
+//! | 256    | tail: sequence number of the next push        |
+//! | 264    | push counter and consumers waiting for data   |
+//! | 384    | `capacity` slots of `size_of::<T>()` bytes    |
+//!
+//! Header fields are native-endian `u64`s and the counters `u32`s, so both
+//! processes must run on the same machine.
+//!
+//! `push_blocking` and `pop_blocking` sleep on the counters with a futex on
+//! Linux, so a waiting process does not spin. Other platforms have no shared
+//! futex in std and fall back to polling with short sleeps.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use memmap2::MmapMut;
+
+const MAGIC: u64 = u64::from_ne_bytes(*b"circusrb");
+const VERSION: u64 = 1;
+
+const HEAD: usize = 128;
+const POPPED: usize = HEAD + 8;
+const ROOM_WAITERS: usize = HEAD + 12;
+const TAIL: usize = 256;
+const PUSHED: usize = TAIL + 8;
+const DATA_WAITERS: usize = TAIL + 12;
+const DATA: usize = 384;
+const MAX_ALIGN: usize = 128; // Largest element alignment `DATA` is a multiple of
+
+pub struct ShmRingBuffer<T> {
+    map: MmapMut,      // Only accessed through `base`
+    base: NonNull<u8>, // From `map.as_mut_ptr()`, so it may be written through
+    capacity: u64,
+    head: u64, // Last values seen, refreshed only when they look stale
+    tail: u64,
+    marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for ShmRingBuffer<T> {}
+unsafe impl<T: Sync> Sync for ShmRingBuffer<T> {}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// File size for `cap` elements of `size` bytes, if it fits in a `u64`.
+fn file_len(cap: u64, size: u64) -> Option<u64> {
+    cap.checked_mul(size)?.checked_add(DATA as u64)
+}
+
+impl<T: Copy> ShmRingBuffer<T> {
+    /// Resizes `file` to hold `cap` elements and lays out an empty buffer in
+    /// it.
+    ///
+    /// # Safety
+    ///
+    /// No other process may have `file` mapped yet.
+    pub unsafe fn create(file: &File, cap: usize) -> io::Result<Self> {
+        if mem::align_of::<T>() > MAX_ALIGN {
+            return Err(invalid("element alignment is too large"));
+        }
+        let len = file_len(cap as u64, mem::size_of::<T>() as u64)
+            .ok_or_else(|| invalid("capacity is too large"))?;
+        file.set_len(len)?;
+        let mut map = MmapMut::map_mut(file)?;
+        let header = [
+            MAGIC,
+            VERSION,
+            cap as u64,
+            mem::size_of::<T>() as u64,
+            mem::align_of::<T>() as u64,
+        ];
+        for (i, field) in header.iter().enumerate() {
+            map[i * 8..(i + 1) * 8].copy_from_slice(&field.to_ne_bytes());
+        }
+        map[HEAD..DATA].fill(0);
+        map.flush()?;
+        Ok(Self::from_map(map, cap as u64))
+    }
+
+    /// Maps a buffer another process made with `create`, checking that its
+    /// header matches `T`.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must have been created for the same `T`, and any bit
+    /// pattern the other process writes must be a valid `T` here, so `T`
+    /// should not hold pointers. Across all processes that map the file, at
+    /// most one may push and at most one may pop.
+    pub unsafe fn open(file: &File) -> io::Result<Self> {
+        if mem::align_of::<T>() > MAX_ALIGN {
+            return Err(invalid("element alignment is too large"));
+        }
+        let map = MmapMut::map_mut(file)?;
+        if map.len() < DATA {
+            return Err(invalid("file is too short for the header"));
+        }
+        let field = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&map[i * 8..(i + 1) * 8]);
+            u64::from_ne_bytes(bytes)
+        };
+        if field(0) != MAGIC || field(1) != VERSION {
+            return Err(invalid("not a ring buffer, or made by another version"));
+        }
+        if field(3) != mem::size_of::<T>() as u64 || field(4) != mem::align_of::<T>() as u64 {
+            return Err(invalid("element layout does not match"));
+        }
+        let capacity = field(2);
+        // The header may be corrupt, so nothing it says can be allowed to overflow
+        let len = file_len(capacity, field(3))
+            .filter(|_| usize::try_from(capacity).is_ok())
+            .ok_or_else(|| invalid("capacity is too large"))?;
+        if (map.len() as u64) < len {
+            return Err(invalid("file is too short for its capacity"));
+        }
+        Ok(Self::from_map(map, capacity))
+    }
+
+    fn from_map(mut map: MmapMut, capacity: u64) -> Self {
+        let base = NonNull::new(map.as_mut_ptr()).expect("mapping is never null");
+        let mut rb = ShmRingBuffer {
+            map,
+            base,
+            capacity,
+            head: 0,
+            tail: 0,
+            marker: PhantomData,
+        };
+        rb.head = rb.index(HEAD).load(Ordering::Acquire);
+        rb.tail = rb.index(TAIL).load(Ordering::Acquire);
+        rb
+    }
+
+    fn index(&self, offset: usize) -> &AtomicU64 {
+        // The map is page-aligned and the offset is a multiple of 8
+        unsafe { &*(self.base.as_ptr().add(offset) as *const AtomicU64) }
+    }
+
+    fn counter(&self, offset: usize) -> &AtomicU32 {
+        unsafe { &*(self.base.as_ptr().add(offset) as *const AtomicU32) }
+    }
+
+    fn slot(&self, seq: u64) -> *mut T {
+        let i = (seq % self.capacity) as usize;
+        unsafe { self.base.as_ptr().add(DATA + i * mem::size_of::<T>()) as *mut T }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// A snapshot that may already be stale when the other process is active.
+    pub fn len(&self) -> usize {
+        let head = self.index(HEAD).load(Ordering::Acquire);
+        let tail = self.index(TAIL).load(Ordering::Acquire);
+        tail.saturating_sub(head) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    // The other process writes the indices, so they are only trusted once
+    // they are consistent: a corrupt header looks full to the producer and
+    // empty to the consumer, rather than overflowing.
+    fn stored(&self) -> Option<u64> {
+        Some(self.tail.wrapping_sub(self.head)).filter(|&n| n <= self.capacity)
+    }
+
+    /// Hands the element back if the buffer is full.
+    pub fn push(&mut self, element: T) -> Result<(), T> {
+        self.tail = self.index(TAIL).load(Ordering::Relaxed);
+        if self.stored().is_none_or(|n| n == self.capacity) {
+            self.head = self.index(HEAD).load(Ordering::Acquire);
+            if self.stored().is_none_or(|n| n == self.capacity) {
+                return Err(element);
+            }
+        }
+        unsafe { ptr::write(self.slot(self.tail), element) };
+        self.tail = self.tail.wrapping_add(1);
+        self.index(TAIL).store(self.tail, Ordering::Release);
+        self.notify(PUSHED, DATA_WAITERS);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.head = self.index(HEAD).load(Ordering::Relaxed);
+        if self.stored().is_none_or(|n| n == 0) {
+            self.tail = self.index(TAIL).load(Ordering::Acquire);
+            if self.stored().is_none_or(|n| n == 0) {
+                return None;
+            }
+        }
+        let element = unsafe { ptr::read(self.slot(self.head)) };
+        self.head = self.head.wrapping_add(1);
+        self.index(HEAD).store(self.head, Ordering::Release);
+        self.notify(POPPED, ROOM_WAITERS);
+        Some(element)
+    }
+
+    /// Sleeps until the consumer makes room for the element.
+    pub fn push_blocking(&mut self, element: T) {
+        let mut pending = Some(element);
+        self.wait_for(POPPED, ROOM_WAITERS, |rb| match rb.push(pending.take()?) {
+            Ok(()) => Some(()),
+            Err(element) => {
+                pending = Some(element);
+                None
+            }
+        })
+    }
+
+    /// Sleeps until the producer pushes an element.
+    pub fn pop_blocking(&mut self) -> T {
+        self.wait_for(PUSHED, DATA_WAITERS, |rb| rb.pop())
+    }
+
+    // A waiter announces itself, reads the counter and tries once more before
+    // sleeping; the futex only sleeps if the counter is still unchanged, so a
+    // notification in between is never lost.
+    fn wait_for<R>(
+        &mut self,
+        events: usize,
+        waiters: usize,
+        mut attempt: impl FnMut(&mut Self) -> Option<R>,
+    ) -> R {
+        loop {
+            if let Some(done) = attempt(self) {
+                return done;
+            }
+            self.counter(waiters).fetch_add(1, Ordering::SeqCst);
+            let seen = self.counter(events).load(Ordering::SeqCst);
+            let done = attempt(self);
+            if done.is_none() {
+                futex::wait(self.counter(events), seen);
+            }
+            self.counter(waiters).fetch_sub(1, Ordering::SeqCst);
+            if let Some(done) = done {
+                return done;
+            }
+        }
+    }
+
+    fn notify(&self, events: usize, waiters: usize) {
+        self.counter(events).fetch_add(1, Ordering::SeqCst);
+        if self.counter(waiters).load(Ordering::SeqCst) != 0 {
+            futex::wake_all(self.counter(events));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod futex {
+    use std::ptr;
+    use std::sync::atomic::AtomicU32;
+
+    // Not FUTEX_PRIVATE_FLAG: the word is shared with other processes.
+    pub(super) fn wait(word: &AtomicU32, expected: u32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT,
+                expected,
+                ptr::null::<libc::timespec>(),
+            );
+        }
+    }
+
+    pub(super) fn wake_all(word: &AtomicU32) {
+        unsafe {
+            libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, i32::MAX);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod futex {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    pub(super) fn wait(word: &AtomicU32, expected: u32) {
+        if word.load(Ordering::SeqCst) == expected {
+            thread::sleep(Duration::from_micros(100));
+        }
+    }
+
+    pub(super) fn wake_all(_word: &AtomicU32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::{Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn temp_file(name: &str) -> (PathBuf, File) {
+        let path = std::env::temp_dir().join(format!("circus-{}-{}", std::process::id(), name));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn shared_test() {
+        let (path, file) = temp_file("shared");
+        let mut producer = unsafe { ShmRingBuffer::<u64>::create(&file, 2) }.unwrap();
+        let mut consumer = unsafe { ShmRingBuffer::<u64>::open(&file) }.unwrap();
+        assert_eq!(consumer.capacity(), 2);
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(producer.push(1), Ok(()));
+        assert_eq!(producer.push(2), Ok(()));
+        assert_eq!(producer.push(3), Err(3));
+        assert!(consumer.is_full());
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(producer.push(3), Ok(()));
+        assert_eq!(consumer.pop(), Some(2));
+        assert_eq!(consumer.pop(), Some(3));
+        assert_eq!(consumer.pop(), None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn blocking_test() {
+        let (path, file) = temp_file("blocking");
+        let mut producer = unsafe { ShmRingBuffer::<u64>::create(&file, 4) }.unwrap();
+        let mut consumer = unsafe { ShmRingBuffer::<u64>::open(&file) }.unwrap();
+        let handle = thread::spawn(move || {
+            for i in 0..1000 {
+                producer.push_blocking(i);
+            }
+        });
+        let received = (0..1000)
+            .map(|_| consumer.pop_blocking())
+            .collect::<Vec<_>>();
+        handle.join().unwrap();
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn header_test() {
+        let (path, file) = temp_file("header");
+        unsafe { ShmRingBuffer::<u64>::create(&file, 4) }.unwrap();
+        let err = unsafe { ShmRingBuffer::<u32>::open(&file) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        file.set_len(DATA as u64).unwrap();
+        let err = unsafe { ShmRingBuffer::<u64>::open(&file) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_capacity_test() {
+        let (path, mut file) = temp_file("corrupt");
+        unsafe { ShmRingBuffer::<u64>::create(&file, 4) }.unwrap();
+        // 2^61 + 1 slots of 8 bytes wrap around to a single slot
+        file.seek(SeekFrom::Start(16)).unwrap();
+        file.write_all(&((1u64 << 61) + 1).to_ne_bytes()).unwrap();
+        let err = unsafe { ShmRingBuffer::<u64>::open(&file) }.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_indices_test() {
+        let (path, mut file) = temp_file("indices");
+        let mut producer = unsafe { ShmRingBuffer::<u64>::create(&file, 4) }.unwrap();
+        (0..4).for_each(|i| producer.push(i).unwrap());
+        // A head past the tail would underflow the element count
+        file.seek(SeekFrom::Start(HEAD as u64)).unwrap();
+        file.write_all(&5u64.to_ne_bytes()).unwrap();
+        let mut consumer = unsafe { ShmRingBuffer::<u64>::open(&file) }.unwrap();
+        assert_eq!(producer.push(4), Err(4));
+        assert_eq!(consumer.pop(), None);
+        assert!(consumer.len() <= consumer.capacity());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn alignment_test() {
+        #[derive(Clone, Copy)]
+        #[repr(align(128))]
+        struct Line(u8);
+        #[derive(Clone, Copy)]
+        #[repr(align(256))]
+        struct Page(u8);
+
+        let (path, file) = temp_file("alignment");
+        let err = unsafe { ShmRingBuffer::<Page>::create(&file, 2) }
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let rb = unsafe { ShmRingBuffer::<Line>::create(&file, 2) }.unwrap();
+        assert!((0..2).all(|seq| (rb.slot(seq) as usize).is_multiple_of(128)));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+use crate::ringbufferu::RingBufferU;
+use std::mem::MaybeUninit;
+
+/// A ring buffer over caller-owned memory, such as a static or an arena
+/// slot. It never allocates; elements still live are dropped with the buffer
+/// and the memory is left for the caller to reuse.
+pub type SliceRingBuffer<'a, T> = RingBufferU<T, &'a mut [MaybeUninit<T>]>;
+
+impl<'a, T> RingBufferU<T, &'a mut [MaybeUninit<T>]> {
+    pub fn from_slice_storage(storage: &'a mut [MaybeUninit<T>]) -> Self {
+        Self::from_storage(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_storage_test() {
+        let mut storage: Vec<MaybeUninit<String>> = (0..3).map(|_| MaybeUninit::uninit()).collect();
+        {
+            let mut rb = SliceRingBuffer::from_slice_storage(&mut storage);
+            assert_eq!(rb.capacity(), 3);
+            for word in ["a", "b", "c", "d"] {
+                rb.push(word.to_string());
+            }
+            assert_eq!(rb.pop(), Some("b".to_string()));
+            assert_eq!(format!("{:?}", rb), r#"["c", "d"]"#);
+        }
+        let mut rb = SliceRingBuffer::from_slice_storage(&mut storage[..2]);
+        assert!(rb.is_empty());
+        rb.extend(vec![1.to_string(), 2.to_string(), 3.to_string()]);
+        assert_eq!(rb.into_vec(), vec!["2", "3"]);
+    }
+}
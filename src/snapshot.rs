@@ -0,0 +1,53 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// An immutable copy of a ring buffer's contents, oldest element first,
+/// taken with `RingBufferU::snapshot` and rolled back to with `restore`.
+///
+/// Cloning a snapshot is cheap: clones share the same elements. A snapshot
+/// also remembers the cursor positions of the buffer it came from, so
+/// cursors taken before the snapshot point at the same elements again after
+/// a restore.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Snapshot<T> {
+    pub(crate) elements: Arc<[T]>,
+    pub(crate) seq: usize,
+}
+
+impl<T> Snapshot<T> {
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+}
+
+impl<T> Deref for Snapshot<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.elements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ringbuffer::RingBuffer;
+
+    #[test]
+    fn restore_test() {
+        let mut rb = RingBuffer::with_capacity(3);
+        rb.extend(1..=4);
+        let cursor = rb.cursor_front();
+        let snapshot = rb.snapshot();
+        assert_eq!(snapshot.as_slice(), &[2, 3, 4]);
+        rb.extend(5..=6);
+        rb.pop();
+        rb.restore(&snapshot);
+        assert_eq!(rb.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(rb.cursor_get(cursor), Ok(&2));
+
+        let mut small = RingBuffer::with_capacity(2);
+        small.restore(&snapshot.clone());
+        assert_eq!(small.iter().copied().collect::<Vec<_>>(), vec![3, 4]);
+        assert!(small.cursor_index(cursor).is_err());
+    }
+}
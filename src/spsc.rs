@@ -0,0 +1,887 @@
+//! A wait-free single-producer single-consumer ring buffer.
+//!
+//! The buffer is split into a `Producer` and a `Consumer` that can live on
+//! different threads. Each side owns one index and only reads the other, so
+//! neither `push` nor `pop` ever loops or locks. Indices are 64-bit sequence
+//! numbers that are never reduced; they would take centuries to wrap.
+
+use std::error::Error;
+use std::fmt;
+use std::future::poll_fn;
+use std::mem::MaybeUninit;
+
+use crate::notify::WakerSlot;
+use crate::padded::CachePadded;
+use crate::sync::{Arc, AtomicBool, AtomicU64, Ordering, UnsafeCell};
+use crate::wait::WaitStrategy;
+
+use std::task::{Context, Poll, Waker};
+
+#[cfg(any(feature = "futures", feature = "tokio"))]
+use std::io;
+#[cfg(any(feature = "futures", feature = "tokio"))]
+use std::pin::Pin;
+
+struct Shared<T> {
+    head: CachePadded<AtomicU64>, // Sequence number of the next element to pop
+    tail: CachePadded<AtomicU64>, // Sequence number of the next element to push
+    closed: AtomicBool,           // Set once either half is dropped or the sink closed
+    readable: WakerSlot,          // A consumer task waiting for elements
+    writable: WakerSlot,          // A producer task waiting for room
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+}
+
+// Slots are only touched by the side that currently owns them, as decided by
+// the head and tail indices.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn slot(&self, seq: u64) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.buffer[(seq % self.buffer.len() as u64) as usize]
+    }
+
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.saturating_sub(head) as usize
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Relaxed);
+        for seq in head..tail {
+            self.slot(seq)
+                .with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+        }
+    }
+}
+
+pub fn with_capacity<T>(cap: usize) -> (Producer<T>, Consumer<T>) {
+    let shared = Arc::new(Shared {
+        head: CachePadded::new(AtomicU64::new(0)),
+        tail: CachePadded::new(AtomicU64::new(0)),
+        closed: AtomicBool::new(false),
+        readable: WakerSlot::new(),
+        writable: WakerSlot::new(),
+        buffer: (0..cap)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+    });
+    let producer = Producer {
+        shared: Arc::clone(&shared),
+        head: 0,
+        tail: 0,
+    };
+    let consumer = Consumer {
+        shared,
+        head: 0,
+        tail: 0,
+    };
+    (producer, consumer)
+}
+
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    head: u64, // Last head seen, refreshed only when the buffer looks full
+    tail: u64,
+}
+
+impl<T> Producer<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Hands the element back if the consumer has not made room for it;
+    /// the producer cannot overwrite slots the consumer may be reading.
+    pub fn push(&mut self, element: T) -> Result<(), T> {
+        if !self.has_room() {
+            return Err(element);
+        }
+        self.shared
+            .slot(self.tail)
+            .with_mut(|slot| unsafe { (*slot).write(element) });
+        self.tail += 1;
+        self.shared.tail.store(self.tail, Ordering::Release);
+        self.shared.readable.wake();
+        Ok(())
+    }
+
+    /// Waits until there is room for the element, so a slow consumer slows
+    /// the producer down instead of losing data. Hands the element back if
+    /// the consumer is dropped.
+    pub async fn push_async(&mut self, element: T) -> Result<(), T> {
+        match poll_fn(|cx| self.poll_room(cx)).await {
+            Ok(()) => self.push(element),
+            Err(Disconnected) => Err(element),
+        }
+    }
+
+    /// Wakes `waker` once the consumer frees a slot or is dropped.
+    ///
+    /// The registration fires once and replaces any earlier one, including
+    /// the one made by a pending `push_async`. Check `is_full` again after
+    /// registering so a pop that raced with it is not missed. A callback can
+    /// be registered by turning an `Arc<impl std::task::Wake>` into a waker.
+    pub fn register_writable(&self, waker: &Waker) {
+        self.shared.writable.register(waker);
+    }
+
+    fn poll_room(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Disconnected>> {
+        for attempt in 0..2 {
+            if self.shared.closed.load(Ordering::Acquire) {
+                return Poll::Ready(Err(Disconnected));
+            }
+            if self.has_room() {
+                return Poll::Ready(Ok(()));
+            }
+            if attempt == 0 {
+                self.shared.writable.register(cx.waker());
+            }
+        }
+        Poll::Pending
+    }
+
+    fn has_room(&mut self) -> bool {
+        let cap = self.capacity() as u64;
+        if self.tail - self.head == cap {
+            self.head = self.shared.head.load(Ordering::Acquire);
+        }
+        self.tail - self.head < cap
+    }
+
+    /// Waits as `strategy` says until the consumer makes room. Hands the
+    /// element back if the consumer is dropped.
+    pub fn push_blocking(&mut self, element: T, strategy: WaitStrategy) -> Result<(), T> {
+        let mut pending = Some(element);
+        strategy.wait_until(|| {
+            let element = pending.take()?;
+            if self.shared.closed.load(Ordering::Acquire) {
+                return Some(Err(element));
+            }
+            match self.push(element) {
+                Ok(()) => Some(Ok(())),
+                Err(element) => {
+                    pending = Some(element);
+                    None
+                }
+            }
+        })
+    }
+
+    /// Number of elements published so far, which is also the sequence
+    /// number the next one will get.
+    pub fn sequence(&self) -> u64 {
+        self.tail
+    }
+
+    // Ends the stream for the consumer, which still reads what is left.
+    fn mark_closed(&self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.readable.wake();
+    }
+
+    /// Claims up to `n` free slots to be filled in place. Nothing becomes
+    /// visible to the consumer until the whole batch is published.
+    pub fn claim(&mut self, n: usize) -> WriteBatch<'_, T> {
+        self.head = self.shared.head.load(Ordering::Acquire);
+        let free = self.capacity() as u64 - (self.tail - self.head);
+        WriteBatch {
+            end: self.tail + free.min(n as u64),
+            written: self.tail,
+            producer: self,
+        }
+    }
+}
+
+/// Slots claimed by `Producer::claim`. Elements written but not published
+/// are dropped with the batch.
+pub struct WriteBatch<'a, T> {
+    producer: &'a mut Producer<T>,
+    written: u64, // Sequence number of the next slot to fill
+    end: u64,
+}
+
+impl<'a, T> WriteBatch<'a, T> {
+    /// Number of claimed slots that are still empty.
+    pub fn remaining(&self) -> usize {
+        (self.end - self.written) as usize
+    }
+
+    /// Hands the element back once every claimed slot is filled.
+    pub fn push(&mut self, element: T) -> Result<(), T> {
+        if self.written == self.end {
+            return Err(element);
+        }
+        self.producer
+            .shared
+            .slot(self.written)
+            .with_mut(|slot| unsafe { (*slot).write(element) });
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Makes every element written so far visible with a single store.
+    pub fn publish(self) {
+        self.producer.tail = self.written;
+        self.producer
+            .shared
+            .tail
+            .store(self.written, Ordering::Release);
+        self.producer.shared.readable.wake();
+    }
+}
+
+impl<'a, T> Drop for WriteBatch<'a, T> {
+    fn drop(&mut self) {
+        for seq in self.producer.tail..self.written {
+            self.producer
+                .shared
+                .slot(seq)
+                .with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+        }
+    }
+}
+
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    head: u64,
+    tail: u64, // Last tail seen, refreshed only when the buffer looks empty
+}
+
+impl<T> Consumer<T> {
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if !self.has_element() {
+            return None;
+        }
+        let element = self
+            .shared
+            .slot(self.head)
+            .with(|slot| unsafe { (*slot).assume_init_read() });
+        self.head += 1;
+        self.shared.head.store(self.head, Ordering::Release);
+        self.shared.writable.wake();
+        Some(element)
+    }
+
+    fn has_element(&mut self) -> bool {
+        if self.head == self.tail {
+            self.tail = self.shared.tail.load(Ordering::Acquire);
+        }
+        self.head != self.tail
+    }
+
+    /// Waits as `strategy` says until there is an element to pop. Fails once
+    /// the producer is dropped or closed and every element is read.
+    pub fn pop_blocking(&mut self, strategy: WaitStrategy) -> Result<T, Disconnected> {
+        strategy.wait_until(|| {
+            if let Some(element) = self.pop() {
+                return Some(Ok(element));
+            }
+            let closed = self.shared.closed.load(Ordering::Acquire);
+            match self.pop() {
+                None if !closed => None,
+                popped => Some(popped.ok_or(Disconnected)),
+            }
+        })
+    }
+
+    /// Sequence number of the next element to pop.
+    pub fn sequence(&self) -> u64 {
+        self.head
+    }
+
+    /// Pops up to `n` elements, releasing their slots to the producer with a
+    /// single store once the batch is dropped.
+    pub fn pop_batch(&mut self, n: usize) -> ReadBatch<'_, T> {
+        self.tail = self.shared.tail.load(Ordering::Acquire);
+        ReadBatch {
+            end: self.head + (self.tail - self.head).min(n as u64),
+            consumer: self,
+        }
+    }
+
+    pub fn barrier(&self) -> SequenceBarrier<T> {
+        SequenceBarrier {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Wakes `waker` once the producer publishes an element or is dropped.
+    ///
+    /// The same rules as for `Producer::register_writable` apply: it fires
+    /// once, replaces the previous waker, and should be followed by another
+    /// check of `is_empty`.
+    pub fn register_readable(&self, waker: &Waker) {
+        self.shared.readable.register(waker);
+    }
+
+    pub fn peek(&mut self) -> Option<&T> {
+        if !self.has_element() {
+            return None;
+        }
+        let slot = self.shared.slot(self.head);
+        Some(slot.with(|slot| unsafe { (*slot).assume_init_ref() }))
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.readable.wake();
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    fn drop(&mut self) {
+        // A forgotten `ReadBatch` never stored the head past the elements it
+        // handed out, and `Shared` must not drop them again
+        self.shared.head.store(self.head, Ordering::Release);
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.writable.wake();
+    }
+}
+
+/// The other half of the buffer is gone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disconnected;
+
+impl fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the other half of the buffer was dropped")
+    }
+}
+
+impl Error for Disconnected {}
+
+/// Ends once the producer is dropped or closed and every element is read.
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for Consumer<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if let Some(element) = this.pop() {
+            return Poll::Ready(Some(element));
+        }
+        this.shared.readable.register(cx.waker());
+        let closed = this.shared.closed.load(Ordering::Acquire);
+        match this.pop() {
+            None if !closed => Poll::Pending,
+            popped => Poll::Ready(popped),
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> futures_sink::Sink<T> for Producer<T> {
+    type Error = Disconnected;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Disconnected>> {
+        self.get_mut().poll_room(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Disconnected> {
+        if self.get_mut().push(item).is_err() {
+            panic!("start_send called without poll_ready");
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Disconnected>> {
+        Poll::Ready(Ok(())) // Pushed elements are visible right away
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Disconnected>> {
+        self.mark_closed();
+        Poll::Ready(Ok(()))
+    }
+}
+
+// Byte pipes: the producer half writes, the consumer half reads and sees the
+// end of the stream once the producer is closed or dropped.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+impl Producer<u8> {
+    fn poll_write_bytes(&mut self, cx: &mut Context<'_>, src: &[u8]) -> Poll<io::Result<usize>> {
+        if src.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        match self.poll_room(cx) {
+            Poll::Ready(Ok(())) => {
+                let mut batch = self.claim(src.len());
+                let n = batch.remaining();
+                for &byte in &src[..n] {
+                    let _ = batch.push(byte);
+                }
+                batch.publish();
+                Poll::Ready(Ok(n))
+            }
+            Poll::Ready(Err(Disconnected)) => {
+                Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, Disconnected)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(any(feature = "futures", feature = "tokio"))]
+impl Consumer<u8> {
+    fn read_bytes(&mut self, dst: &mut [u8]) -> usize {
+        let batch = self.pop_batch(dst.len());
+        dst.iter_mut()
+            .zip(batch)
+            .map(|(slot, byte)| *slot = byte)
+            .count()
+    }
+
+    fn poll_read_bytes(&mut self, cx: &mut Context<'_>, dst: &mut [u8]) -> Poll<usize> {
+        let n = self.read_bytes(dst);
+        if n > 0 || dst.is_empty() {
+            return Poll::Ready(n);
+        }
+        self.shared.readable.register(cx.waker());
+        let closed = self.shared.closed.load(Ordering::Acquire);
+        match self.read_bytes(dst) {
+            0 if !closed => Poll::Pending,
+            n => Poll::Ready(n),
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl futures_io::AsyncWrite for Producer<u8> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_bytes(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.mark_closed();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl futures_io::AsyncRead for Consumer<u8> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_bytes(cx, buf).map(Ok)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for Producer<u8> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_write_bytes(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.mark_closed();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for Consumer<u8> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = match self
+            .get_mut()
+            .poll_read_bytes(cx, buf.initialize_unfilled())
+        {
+            Poll::Ready(n) => n,
+            Poll::Pending => return Poll::Pending,
+        };
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Elements taken by `Consumer::pop_batch`; the ones not iterated over are
+/// dropped with the batch.
+pub struct ReadBatch<'a, T> {
+    consumer: &'a mut Consumer<T>,
+    end: u64,
+}
+
+impl<'a, T> Iterator for ReadBatch<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let consumer = &mut *self.consumer;
+        if consumer.head == self.end {
+            return None;
+        }
+        let element = consumer
+            .shared
+            .slot(consumer.head)
+            .with(|slot| unsafe { (*slot).assume_init_read() });
+        consumer.head += 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.consumer.head) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ReadBatch<'a, T> {}
+
+impl<'a, T> Drop for ReadBatch<'a, T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+        let consumer = &self.consumer;
+        consumer.shared.head.store(consumer.head, Ordering::Release);
+        consumer.shared.writable.wake();
+    }
+}
+
+/// Lets any thread follow how far the producer has published.
+pub struct SequenceBarrier<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for SequenceBarrier<T> {
+    fn clone(&self) -> Self {
+        SequenceBarrier {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> SequenceBarrier<T> {
+    /// Number of elements published so far.
+    pub fn published(&self) -> u64 {
+        self.shared.tail.load(Ordering::Acquire)
+    }
+
+    /// Waits until the element with sequence number `seq` and all before it
+    /// are published, returning the number of published elements.
+    pub fn wait_for(&self, seq: u64, strategy: WaitStrategy) -> u64 {
+        strategy.wait_until(|| Some(self.published()).filter(|&published| published > seq))
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn push_pop_test() {
+        let (mut producer, mut consumer) = with_capacity(2);
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(producer.push(1), Ok(()));
+        assert_eq!(producer.push(2), Ok(()));
+        assert_eq!(producer.push(3), Err(3));
+        assert!(producer.is_full());
+        assert_eq!(consumer.peek(), Some(&1));
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(producer.push(3), Ok(()));
+        assert_eq!(consumer.pop(), Some(2));
+        assert_eq!(consumer.pop(), Some(3));
+        assert_eq!(consumer.pop(), None);
+
+        let (mut producer, mut consumer) = with_capacity(0);
+        assert_eq!(producer.push(1), Err(1));
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn threads_test() {
+        let (mut producer, mut consumer) = with_capacity(7);
+        let handle = thread::spawn(move || {
+            for i in 0..10_000u32 {
+                producer
+                    .push_blocking(i, WaitStrategy::SpinThenYield)
+                    .unwrap();
+            }
+        });
+        for expected in 0..10_000 {
+            assert_eq!(consumer.pop_blocking(WaitStrategy::default()), Ok(expected));
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn blocking_disconnect_test() {
+        let (mut producer, consumer) = with_capacity(1);
+        producer.push(1).unwrap();
+        let handle = thread::spawn(move || producer.push_blocking(2, WaitStrategy::default()));
+        thread::sleep(std::time::Duration::from_millis(10));
+        drop(consumer);
+        assert_eq!(handle.join().unwrap(), Err(2));
+
+        let (mut producer, mut consumer) = with_capacity(2);
+        producer.push(1).unwrap();
+        let handle = thread::spawn(move || {
+            let popped = consumer.pop_blocking(WaitStrategy::default());
+            (popped, consumer.pop_blocking(WaitStrategy::default()))
+        });
+        drop(producer);
+        assert_eq!(handle.join().unwrap(), (Ok(1), Err(Disconnected)));
+    }
+
+    #[test]
+    fn drop_test() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let (mut producer, mut consumer) = with_capacity(3);
+        for _ in 0..3 {
+            producer.push(Rc::clone(&counter)).unwrap();
+        }
+        consumer.pop();
+        drop(producer);
+        drop(consumer);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn forgotten_batch_test() {
+        use std::rc::Rc;
+        let counter = Rc::new(());
+        let (mut producer, mut consumer) = with_capacity(2);
+        for _ in 0..2 {
+            producer.push(Rc::clone(&counter)).unwrap();
+        }
+        let mut batch = consumer.pop_batch(2);
+        let popped = batch.by_ref().collect::<Vec<_>>();
+        std::mem::forget(batch);
+        drop(producer);
+        drop(consumer);
+        assert_eq!(Rc::strong_count(&counter), 3);
+        drop(popped);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn batch_test() {
+        let (mut producer, mut consumer) = with_capacity(4);
+        let barrier = consumer.barrier();
+        producer.push(0).unwrap();
+        let mut batch = producer.claim(5);
+        assert_eq!(batch.remaining(), 3);
+        batch.push(1).unwrap();
+        batch.push(2).unwrap();
+        assert_eq!(barrier.published(), 1);
+        batch.publish();
+        assert_eq!(barrier.wait_for(2, WaitStrategy::Spin), 3);
+        let mut batch = producer.claim(1);
+        batch.push(3).unwrap();
+        assert_eq!(batch.push(4), Err(4));
+        drop(batch); // Never published
+        assert_eq!(producer.sequence(), 3);
+        assert_eq!(consumer.pop_batch(2).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(consumer.sequence(), 2);
+        assert_eq!(producer.claim(10).remaining(), 3);
+        assert_eq!(consumer.pop_batch(10).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn register_test() {
+        use std::sync::atomic::AtomicUsize;
+        use std::task::Wake;
+
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(Arc::clone(&counter));
+        let (mut producer, mut consumer) = with_capacity(1);
+        consumer.register_readable(&waker);
+        producer.push(1).unwrap();
+        producer.register_writable(&waker);
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+        assert_eq!(producer.push(2), Err(2));
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+        producer.push(2).unwrap();
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2); // Nobody registered
+    }
+
+    #[test]
+    fn push_async_test() {
+        use futures::executor::block_on;
+        let (mut producer, mut consumer) = with_capacity(2);
+        let handle = thread::spawn(move || {
+            block_on(async {
+                for i in 0..1002 {
+                    producer.push_async(i).await.unwrap();
+                }
+                producer.push_async(1002).await
+            })
+        });
+        for expected in 0..1000 {
+            assert_eq!(consumer.pop_blocking(WaitStrategy::default()), Ok(expected));
+        }
+        while !consumer.is_full() {
+            thread::yield_now();
+        }
+        drop(consumer);
+        assert_eq!(handle.join().unwrap(), Err(1002));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_sink_test() {
+        use futures::executor::block_on;
+        use futures::{stream, SinkExt, StreamExt};
+        let (mut producer, consumer) = with_capacity(4);
+        let handle = thread::spawn(move || {
+            block_on(producer.send_all(&mut stream::iter((0..1000).map(Ok))))?;
+            block_on(producer.close())
+        });
+        let received = block_on(consumer.collect::<Vec<_>>());
+        assert_eq!(handle.join().unwrap(), Ok(()));
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+
+        let (mut producer, consumer) = with_capacity(1);
+        drop(consumer);
+        assert_eq!(block_on(producer.send(1)), Err(Disconnected));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn futures_io_test() {
+        use futures::executor::block_on;
+        use futures::{AsyncReadExt, AsyncWriteExt};
+        let data = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+        let (mut producer, mut consumer) = with_capacity(16);
+        let handle = {
+            let data = data.clone();
+            thread::spawn(move || {
+                block_on(producer.write_all(&data))?;
+                block_on(producer.close())
+            })
+        };
+        let mut received = Vec::new();
+        block_on(consumer.read_to_end(&mut received)).unwrap();
+        handle.join().unwrap().unwrap();
+        assert_eq!(received, data);
+
+        let (mut producer, consumer) = with_capacity(1);
+        drop(consumer);
+        let err = block_on(producer.write(b"x")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_io_test() {
+        use futures::executor::block_on;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let data = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+        let (mut producer, mut consumer) = with_capacity(16);
+        let handle = {
+            let data = data.clone();
+            thread::spawn(move || {
+                block_on(producer.write_all(&data))?;
+                block_on(producer.shutdown())
+            })
+        };
+        let mut received = Vec::new();
+        block_on(consumer.read_to_end(&mut received)).unwrap();
+        handle.join().unwrap().unwrap();
+        assert_eq!(received, data);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn push_pop_test() {
+        loom::model(|| {
+            let (mut producer, mut consumer) = with_capacity(1);
+            let handle = thread::spawn(move || {
+                for i in 0..2 {
+                    producer
+                        .push_blocking(i.to_string(), WaitStrategy::Spin)
+                        .unwrap();
+                }
+            });
+            for i in 0..2 {
+                assert_eq!(consumer.pop_blocking(WaitStrategy::Spin), Ok(i.to_string()));
+            }
+            handle.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn batch_test() {
+        loom::model(|| {
+            let (mut producer, mut consumer) = with_capacity(2);
+            let handle = thread::spawn(move || {
+                let mut batch = producer.claim(2);
+                batch.push(1).unwrap();
+                batch.push(2).unwrap();
+                batch.publish();
+            });
+            let popped = consumer.pop_batch(2).collect::<Vec<_>>();
+            assert!(popped.is_empty() || popped == [1, 2]);
+            handle.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,480 @@
+use crate::monotonic::MonotonicRingBuffer;
+use crate::ringbuffer::RingBuffer;
+use crate::ringbufferu::End;
+use std::cmp::Ordering;
+use std::ops::{Add, Sub};
+
+/// Numeric types that rolling statistics can be computed over.
+pub trait Sample: Copy + PartialOrd {
+    /// Sums are kept in this type, wide enough that a window of narrow
+    /// samples cannot overflow it.
+    type Sum: Copy + Add<Output = Self::Sum> + Sub<Output = Self::Sum>;
+
+    const ZERO: Self::Sum;
+
+    fn widen(self) -> Self::Sum;
+
+    fn to_f64(self) -> f64;
+
+    fn sum_to_f64(sum: Self::Sum) -> f64;
+}
+
+macro_rules! impl_sample {
+    ($sum:ty, $zero:expr => $($t:ty),*) => {
+        $(
+            impl Sample for $t {
+                type Sum = $sum;
+
+                const ZERO: $sum = $zero;
+
+                fn widen(self) -> $sum {
+                    self as $sum
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn sum_to_f64(sum: $sum) -> f64 {
+                    sum as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_sample!(i128, 0 => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize);
+impl_sample!(u128, 0 => u128);
+impl_sample!(f64, 0.0 => f32, f64);
+
+/// Sum, mean, minimum and maximum of the last `capacity` samples, updated as
+/// samples enter and leave the window instead of being recomputed.
+///
+/// The sum is kept in `Sample::Sum`, so it cannot overflow for samples
+/// narrower than 128 bits. For floats it is maintained by adding and
+/// subtracting, so it can drift from a freshly computed sum by rounding error
+/// over very long streams.
+pub struct RollingStats<T: Sample> {
+    window: MonotonicRingBuffer<T>,
+    sum: T::Sum,
+}
+
+impl<T: Sample> RollingStats<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        RollingStats {
+            window: MonotonicRingBuffer::with_capacity(cap),
+            sum: T::ZERO,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.window.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Returns the oldest sample if it had to be evicted.
+    pub fn push(&mut self, sample: T) -> Option<T> {
+        let evicted = self.window.push(sample);
+        if let Some(old) = evicted {
+            self.sum = self.sum - old.widen();
+        }
+        self.sum = self.sum + sample.widen();
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let popped = self.window.pop()?;
+        self.sum = self.sum - popped.widen();
+        Some(popped)
+    }
+
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.sum = T::ZERO;
+    }
+
+    pub fn sum(&self) -> T::Sum {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(T::sum_to_f64(self.sum) / self.len() as f64)
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.window.min().copied()
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.window.max().copied()
+    }
+
+    pub fn window(&self) -> &RingBuffer<T> {
+        self.window.buffer()
+    }
+}
+
+/// Exact moving median and quantiles over the last `capacity` samples.
+///
+/// Keeps a sorted copy of the window next to it, so every push and pop is
+/// O(capacity) while queries are O(1). Samples must be totally ordered among
+/// themselves: pushing a NaN panics.
+pub struct RollingQuantiles<T> {
+    window: RingBuffer<T>,
+    sorted: RingBuffer<T>,
+}
+
+fn compare<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).expect("samples must be comparable")
+}
+
+impl<T: Sample> RollingQuantiles<T> {
+    pub fn with_capacity(cap: usize) -> Self {
+        RollingQuantiles {
+            window: RingBuffer::with_capacity(cap),
+            sorted: RingBuffer::with_capacity(cap),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.window.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    /// Returns the oldest sample if it had to be evicted.
+    pub fn push(&mut self, sample: T) -> Option<T> {
+        compare(&sample, &sample); // Reject NaN before touching the window
+        if self.window.capacity() == 0 {
+            return Some(sample);
+        }
+        let evicted = self.window.push(sample);
+        if let Some(old) = evicted {
+            self.forget(old);
+        }
+        self.sorted.insert_sorted_by(sample, End::Front, compare);
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let popped = self.window.pop()?;
+        self.forget(popped);
+        Some(popped)
+    }
+
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.sorted.clear();
+    }
+
+    /// The middle sample, or the mean of the two middle samples for even lengths.
+    pub fn median(&self) -> Option<f64> {
+        let len = self.sorted.len();
+        if len == 0 {
+            return None;
+        }
+        let upper = self.sorted[len / 2].to_f64();
+        if len % 2 == 1 {
+            return Some(upper);
+        }
+        Some((self.sorted[len / 2 - 1].to_f64() + upper) / 2.0)
+    }
+
+    /// The sample at rank `q` (0.0 is the minimum, 1.0 the maximum),
+    /// using the nearest-rank method.
+    pub fn quantile(&self, q: f64) -> Option<T> {
+        assert!((0.0..=1.0).contains(&q), "quantile must be within [0, 1]");
+        let last = self.sorted.len().checked_sub(1)?;
+        let rank = (q * last as f64).round() as usize;
+        Some(self.sorted[rank])
+    }
+
+    pub fn window(&self) -> &RingBuffer<T> {
+        &self.window
+    }
+
+    fn forget(&mut self, sample: T) {
+        let idx = self
+            .sorted
+            .binary_search_by(|x| compare(x, &sample))
+            .expect("sorted copy is out of sync with the window");
+        self.sorted.remove(idx);
+    }
+}
+
+/// A window of raw samples together with their exponentially weighted moving
+/// average. The average covers every sample ever pushed, with weight `alpha`
+/// for the newest one, and is not affected by eviction or `pop`.
+pub struct Ewma<T> {
+    window: RingBuffer<T>,
+    alpha: f64,
+    smoothed: Option<f64>,
+}
+
+impl<T: Sample> Ewma<T> {
+    pub fn with_capacity(cap: usize, alpha: f64) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be within (0, 1]");
+        Ewma {
+            window: RingBuffer::with_capacity(cap),
+            alpha,
+            smoothed: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.window.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns the oldest sample if it had to be evicted.
+    pub fn push(&mut self, sample: T) -> Option<T> {
+        let x = sample.to_f64();
+        self.smoothed = Some(match self.smoothed {
+            Some(previous) => self.alpha * x + (1.0 - self.alpha) * previous,
+            None => x,
+        });
+        self.window.push(sample)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.window.pop()
+    }
+
+    /// Empties the window and forgets the average.
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.smoothed = None;
+    }
+
+    pub fn smoothed(&self) -> Option<f64> {
+        self.smoothed
+    }
+
+    pub fn window(&self) -> &RingBuffer<T> {
+        &self.window
+    }
+}
+
+/// Counts of the last `capacity` samples per bucket, updated as samples enter
+/// and leave the window.
+///
+/// Bucket `i` holds samples in `(bounds[i - 1], bounds[i]]`; the first bucket
+/// is unbounded below and an extra last bucket holds samples above every bound.
+pub struct RollingHistogram<T> {
+    window: RingBuffer<T>,
+    bounds: Vec<T>,
+    counts: Vec<usize>,
+}
+
+impl<T: Sample> RollingHistogram<T> {
+    pub fn with_bounds(cap: usize, bounds: Vec<T>) -> Self {
+        assert!(
+            bounds.windows(2).all(|pair| pair[0] < pair[1]),
+            "bucket bounds must be strictly increasing"
+        );
+        RollingHistogram {
+            window: RingBuffer::with_capacity(cap),
+            counts: vec![0; bounds.len() + 1],
+            bounds,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.window.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    pub fn bucket_of(&self, sample: T) -> usize {
+        self.bounds.partition_point(|&bound| bound < sample)
+    }
+
+    /// Returns the oldest sample if it had to be evicted.
+    pub fn push(&mut self, sample: T) -> Option<T> {
+        let bucket = self.bucket_of(sample);
+        self.counts[bucket] += 1;
+        let evicted = self.window.push(sample);
+        if let Some(old) = evicted {
+            self.forget(old);
+        }
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let popped = self.window.pop()?;
+        self.forget(popped);
+        Some(popped)
+    }
+
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.counts.iter_mut().for_each(|count| *count = 0);
+    }
+
+    pub fn bounds(&self) -> &[T] {
+        &self.bounds
+    }
+
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// The bucket holding the sample at rank `q` (0.0 is the minimum, 1.0 the
+    /// maximum), using the nearest-rank method.
+    pub fn quantile_bucket(&self, q: f64) -> Option<usize> {
+        assert!((0.0..=1.0).contains(&q), "quantile must be within [0, 1]");
+        let last = self.len().checked_sub(1)?;
+        let rank = (q * last as f64).round() as usize;
+        let mut seen = 0;
+        self.counts.iter().position(|&count| {
+            seen += count;
+            seen > rank
+        })
+    }
+
+    pub fn window(&self) -> &RingBuffer<T> {
+        &self.window
+    }
+
+    fn forget(&mut self, sample: T) {
+        let bucket = self.bucket_of(sample);
+        self.counts[bucket] -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_stats_test() {
+        let mut stats = RollingStats::<i32>::with_capacity(3);
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.sum(), 0);
+        for &x in &[4, 8, 6, 1] {
+            stats.push(x);
+        }
+        assert_eq!(stats.sum(), 15);
+        assert_eq!(stats.mean(), Some(5.0));
+        assert_eq!((stats.min(), stats.max()), (Some(1), Some(8)));
+        assert_eq!(stats.pop(), Some(8));
+        assert_eq!(stats.sum(), 7);
+        assert_eq!((stats.min(), stats.max()), (Some(1), Some(6)));
+    }
+
+    #[test]
+    fn float_stats_test() {
+        let mut stats = RollingStats::with_capacity(2);
+        stats.push(0.5);
+        stats.push(1.5);
+        stats.push(2.5);
+        assert_eq!(stats.sum(), 4.0);
+        assert_eq!(stats.mean(), Some(2.0));
+        assert_eq!(stats.window().iter().collect::<Vec<_>>(), vec![&1.5, &2.5]);
+    }
+
+    #[test]
+    fn narrow_stats_test() {
+        let mut stats = RollingStats::<u8>::with_capacity(4);
+        for _ in 0..6 {
+            stats.push(100);
+        }
+        assert_eq!(stats.sum(), 400);
+        assert_eq!(stats.mean(), Some(100.0));
+    }
+
+    #[test]
+    fn rolling_median_test() {
+        let mut quantiles = RollingQuantiles::with_capacity(4);
+        assert_eq!(quantiles.median(), None);
+        assert_eq!(quantiles.quantile(0.5), None);
+        let mut medians = Vec::new();
+        for &x in &[5, 1, 9, 3, 7, 7, 2] {
+            quantiles.push(x);
+            medians.push(quantiles.median().unwrap());
+        }
+        assert_eq!(medians, vec![5.0, 3.0, 5.0, 4.0, 5.0, 7.0, 5.0]);
+        assert_eq!(quantiles.quantile(0.0), Some(2));
+        assert_eq!(quantiles.quantile(1.0), Some(7));
+        assert_eq!(quantiles.quantile(0.25), Some(3));
+    }
+
+    #[test]
+    fn rolling_quantiles_pop_test() {
+        let mut quantiles = RollingQuantiles::with_capacity(5);
+        for &x in &[0.5, 4.0, 2.0] {
+            quantiles.push(x);
+        }
+        assert_eq!(quantiles.pop(), Some(0.5));
+        assert_eq!(quantiles.median(), Some(3.0));
+        assert_eq!(quantiles.quantile(0.0), Some(2.0));
+        let mut empty = RollingQuantiles::with_capacity(0);
+        assert_eq!(empty.push(1.5), Some(1.5));
+        assert_eq!(empty.median(), None);
+    }
+
+    #[test]
+    fn ewma_test() {
+        let mut ewma = Ewma::with_capacity(2, 0.5);
+        assert_eq!(ewma.smoothed(), None);
+        ewma.push(4);
+        assert_eq!(ewma.smoothed(), Some(4.0));
+        ewma.push(8);
+        assert_eq!(ewma.smoothed(), Some(6.0));
+        assert_eq!(ewma.push(0), Some(4));
+        assert_eq!(ewma.smoothed(), Some(3.0));
+        assert_eq!(ewma.window().iter().collect::<Vec<_>>(), vec![&8, &0]);
+        ewma.clear();
+        assert_eq!(ewma.smoothed(), None);
+    }
+
+    #[test]
+    fn rolling_histogram_test() {
+        let mut histogram = RollingHistogram::with_bounds(5, vec![10, 20, 50]);
+        assert_eq!(histogram.quantile_bucket(0.5), None);
+        for &latency in &[3, 12, 10, 70, 25, 48] {
+            histogram.push(latency);
+        }
+        assert_eq!(histogram.counts(), &[1, 1, 2, 1]);
+        assert_eq!(histogram.quantile_bucket(0.0), Some(0));
+        assert_eq!(histogram.quantile_bucket(0.5), Some(2));
+        assert_eq!(histogram.quantile_bucket(1.0), Some(3));
+        assert_eq!(histogram.pop(), Some(12));
+        assert_eq!(histogram.counts(), &[1, 0, 2, 1]);
+        histogram.clear();
+        assert_eq!(histogram.counts(), &[0, 0, 0, 0]);
+    }
+}
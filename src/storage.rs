@@ -0,0 +1,164 @@
+use std::alloc::{self, Layout};
+use std::mem::{self, MaybeUninit};
+use std::ptr::NonNull;
+use std::slice;
+
+/// Backing memory for a `RingBufferU`: a fixed run of slots that may or may
+/// not hold initialized elements. The ring buffer keeps track of which slots
+/// are live and drops them itself; storage never reads or drops its contents.
+///
+/// # Safety
+///
+/// Both methods must return the same memory with the same length every time
+/// they are called, as long as the storage is not moved out of the buffer.
+pub unsafe trait Storage<T> {
+    fn as_slice(&self) -> &[MaybeUninit<T>];
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>];
+}
+
+unsafe impl<T> Storage<T> for Vec<MaybeUninit<T>> {
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        self
+    }
+}
+
+unsafe impl<T> Storage<T> for Box<[MaybeUninit<T>]> {
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        self
+    }
+}
+
+unsafe impl<T, const N: usize> Storage<T> for [MaybeUninit<T>; N] {
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        self
+    }
+}
+
+unsafe impl<T> Storage<T> for &mut [MaybeUninit<T>] {
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        self
+    }
+}
+
+#[cfg(feature = "allocator-api2")]
+unsafe impl<T, A: allocator_api2::alloc::Allocator> Storage<T>
+    for allocator_api2::vec::Vec<MaybeUninit<T>, A>
+{
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        self
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        self
+    }
+}
+
+/// Heap storage whose first slot starts on an `align`-byte boundary, such
+/// as a cache line (64) or a page (4096), for DMA engines and SIMD code.
+///
+/// Slots are laid out back to back, so every slot is only as aligned as `T`
+/// itself. To align each slot, store an over-aligned element type, such as
+/// `CachePadded<T>` or a `#[repr(align(N))]` wrapper.
+pub struct AlignedStorage<T> {
+    ptr: NonNull<MaybeUninit<T>>,
+    len: usize,
+    layout: Layout,
+}
+
+unsafe impl<T: Send> Send for AlignedStorage<T> {}
+unsafe impl<T: Sync> Sync for AlignedStorage<T> {}
+
+impl<T> AlignedStorage<T> {
+    /// Allocates `len` uninitialized slots. Alignments below that of `T` are
+    /// raised to it.
+    ///
+    /// # Panics
+    ///
+    /// If `align` is not a power of two or the size overflows.
+    pub fn new(len: usize, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        let size = mem::size_of::<T>()
+            .checked_mul(len)
+            .expect("capacity overflow");
+        let layout = Layout::from_size_align(size, align.max(mem::align_of::<T>()))
+            .expect("capacity overflow");
+        let ptr = if size == 0 {
+            // Nothing to allocate, but the pointer must still be aligned
+            NonNull::new(layout.align() as *mut MaybeUninit<T>).unwrap()
+        } else {
+            let raw = unsafe { alloc::alloc(layout) };
+            NonNull::new(raw.cast()).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+        };
+        AlignedStorage { ptr, len, layout }
+    }
+
+    /// The alignment of the first slot.
+    pub fn align(&self) -> usize {
+        self.layout.align()
+    }
+}
+
+impl<T> Drop for AlignedStorage<T> {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { alloc::dealloc(self.ptr.as_ptr().cast(), self.layout) };
+        }
+    }
+}
+
+unsafe impl<T> Storage<T> for AlignedStorage<T> {
+    fn as_slice(&self) -> &[MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ringbufferu::RingBufferU;
+
+    #[repr(align(64))]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Line(u8);
+
+    #[test]
+    fn aligned_test() {
+        let mut rb = RingBufferU::<u8, _>::with_capacity_aligned(100, 4096);
+        rb.extend_from_slice(&[1; 150]);
+        assert_eq!(rb.len(), 100);
+        assert_eq!(rb.storage().align(), 4096);
+        assert!((rb.as_slices().0.as_ptr() as usize).is_multiple_of(4096));
+
+        let mut slots = RingBufferU::<Line, _>::with_capacity_aligned(3, 1);
+        for i in 0..4 {
+            slots.push(Line(i));
+        }
+        assert!(slots
+            .iter()
+            .all(|x| (x as *const Line as usize).is_multiple_of(64)));
+        assert_eq!(slots.pop(), Some(Line(1)));
+
+        let empty = RingBufferU::<(), _>::with_capacity_aligned(4, 256);
+        assert_eq!(empty.capacity(), 4);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::ringbuffer::RingBuffer;
+use std::time::{Duration, Instant};
+
+/// A ring buffer that forgets elements older than `max_age` as well as the
+/// oldest ones once `capacity` is reached.
+///
+/// Expiry is applied lazily whenever the buffer is pushed to, popped or
+/// peeked; `len` and `iter` may include stale elements until then. The `_at`
+/// variants take the current time explicitly. With `max_age` set to
+/// `Duration::MAX` elements only leave by count, which makes the buffer a plain
+/// throughput monitor via `rate` and `span`.
+pub struct TimedRingBuffer<T> {
+    buffer: RingBuffer<(Instant, T)>,
+    max_age: Duration,
+}
+
+impl<T> TimedRingBuffer<T> {
+    pub fn with_capacity(cap: usize, max_age: Duration) -> Self {
+        TimedRingBuffer {
+            buffer: RingBuffer::with_capacity(cap),
+            max_age,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Drops every element older than `max_age`, returning how many were dropped.
+    pub fn expire(&mut self) -> usize {
+        self.expire_at(Instant::now())
+    }
+
+    pub fn expire_at(&mut self, now: Instant) -> usize {
+        let max_age = self.max_age;
+        let mut expired = 0;
+        while self
+            .buffer
+            .pop_if(|(pushed, _)| now.saturating_duration_since(*pushed) > max_age)
+            .is_some()
+        {
+            expired += 1;
+        }
+        expired
+    }
+
+    /// Returns the oldest element if it had to be overwritten to make room;
+    /// expired elements are dropped silently.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        self.push_at(element, Instant::now())
+    }
+
+    pub fn push_at(&mut self, element: T, now: Instant) -> Option<T> {
+        self.expire_at(now);
+        self.buffer.push((now, element)).map(|(_, evicted)| evicted)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_at(Instant::now())
+    }
+
+    pub fn pop_at(&mut self, now: Instant) -> Option<T> {
+        self.expire_at(now);
+        self.buffer.pop().map(|(_, element)| element)
+    }
+
+    pub fn front(&mut self) -> Option<&T> {
+        self.front_at(Instant::now())
+    }
+
+    pub fn front_at(&mut self, now: Instant) -> Option<&T> {
+        self.expire_at(now);
+        self.buffer.front().map(|(_, element)| element)
+    }
+
+    pub fn back(&mut self) -> Option<&T> {
+        self.back_at(Instant::now())
+    }
+
+    pub fn back_at(&mut self, now: Instant) -> Option<&T> {
+        self.expire_at(now);
+        self.buffer.back().map(|(_, element)| element)
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Age of the oldest element that has not expired yet.
+    pub fn span(&self) -> Option<Duration> {
+        self.span_at(Instant::now())
+    }
+
+    pub fn span_at(&self, now: Instant) -> Option<Duration> {
+        let (oldest, _) = self.live_at(now).next()?;
+        Some(now.saturating_duration_since(*oldest))
+    }
+
+    /// Events per second over the live window: the number of elements that
+    /// have not expired divided by the age of the oldest of them.
+    pub fn rate(&self) -> Option<f64> {
+        self.rate_at(Instant::now())
+    }
+
+    pub fn rate_at(&self, now: Instant) -> Option<f64> {
+        let span = self.span_at(now)?.as_secs_f64();
+        if span == 0.0 {
+            return None;
+        }
+        Some(self.live_at(now).count() as f64 / span)
+    }
+
+    fn live_at(&self, now: Instant) -> impl Iterator<Item = &(Instant, T)> {
+        let max_age = self.max_age;
+        self.buffer
+            .iter()
+            .skip_while(move |(pushed, _)| now.saturating_duration_since(*pushed) > max_age)
+    }
+
+    /// Iterates over elements with the time they were pushed, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (Instant, &T)> + '_ {
+        self.buffer
+            .iter()
+            .map(|(pushed, element)| (*pushed, element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expire_by_age_test() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut rb = TimedRingBuffer::with_capacity(10, Duration::from_millis(100));
+        rb.push_at(1, at(0));
+        rb.push_at(2, at(50));
+        rb.push_at(3, at(120));
+        assert_eq!(rb.len(), 2);
+        assert_eq!(rb.front_at(at(150)), Some(&2));
+        assert_eq!(rb.back_at(at(151)), Some(&3));
+        assert_eq!(rb.pop_at(at(200)), Some(3));
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn expire_by_count_test() {
+        let t0 = Instant::now();
+        let mut rb = TimedRingBuffer::with_capacity(2, Duration::from_secs(60));
+        assert_eq!(rb.push_at('a', t0), None);
+        assert_eq!(rb.push_at('b', t0), None);
+        assert_eq!(rb.push_at('c', t0), Some('a'));
+        assert_eq!(rb.expire_at(t0 + Duration::from_secs(61)), 2);
+        assert_eq!(rb.iter().count(), 0);
+    }
+
+    #[test]
+    fn rate_span_test() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut rb = TimedRingBuffer::with_capacity(100, Duration::from_secs(1));
+        assert_eq!(rb.span_at(t0), None);
+        assert_eq!(rb.rate_at(t0), None);
+        for ms in (0..2000).step_by(250) {
+            rb.push_at((), at(ms));
+        }
+        assert_eq!(rb.span_at(at(2000)), Some(Duration::from_millis(1000)));
+        assert_eq!(rb.rate_at(at(2000)), Some(4.0));
+        assert_eq!(rb.span_at(at(2100)), Some(Duration::from_millis(850)));
+
+        let mut monitor = TimedRingBuffer::with_capacity(4, Duration::MAX);
+        for ms in (0..1000).step_by(100) {
+            monitor.push_at(ms, at(ms));
+        }
+        assert_eq!(monitor.span_at(at(1000)), Some(Duration::from_millis(400)));
+        assert_eq!(monitor.rate_at(at(1000)), Some(10.0));
+    }
+}
@@ -0,0 +1,104 @@
+//! Groups of pushes that land all at once or not at all.
+//!
+//! `RingBufferU::begin` returns a `Transaction` that stages pushes on the
+//! side. The buffer is untouched until `commit`, which pushes the staged
+//! elements in order and only then evicts old ones to make room. `rollback`,
+//! or dropping the transaction, e.g. while unwinding from a panic halfway
+//! through a multi-part record, discards them instead.
+
+use crate::ringbufferu::RingBufferU;
+use crate::storage::Storage;
+
+impl<T, S: Storage<T>> RingBufferU<T, S> {
+    /// Starts staging pushes; see `Transaction`.
+    pub fn begin(&mut self) -> Transaction<'_, T, S> {
+        Transaction {
+            buffer: self,
+            staged: Vec::new(),
+        }
+    }
+}
+
+/// Pushes staged by `RingBufferU::begin`, rolled back if dropped without a
+/// `commit`.
+pub struct Transaction<'a, T, S: Storage<T> = Vec<std::mem::MaybeUninit<T>>> {
+    buffer: &'a mut RingBufferU<T, S>,
+    staged: Vec<T>,
+}
+
+impl<'a, T, S: Storage<T>> Transaction<'a, T, S> {
+    pub fn push(&mut self, element: T) {
+        self.staged.push(element);
+    }
+
+    /// Number of staged elements.
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// The buffer as it was before the transaction began.
+    pub fn buffer(&self) -> &RingBufferU<T, S> {
+        self.buffer
+    }
+
+    /// Pushes every staged element, returning the old elements evicted to
+    /// make room, oldest first. If more elements were staged than fit, the
+    /// first ones are evicted right away and returned too.
+    pub fn commit(mut self) -> Vec<T> {
+        let staged = std::mem::take(&mut self.staged);
+        staged
+            .into_iter()
+            .filter_map(|element| self.buffer.push(element))
+            .collect()
+    }
+
+    /// Discards the staged elements; same as dropping the transaction.
+    pub fn rollback(self) {}
+}
+
+impl<'a, T, S: Storage<T>> Extend<T> for Transaction<'a, T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.staged.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ringbuffer::RingBuffer;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn commit_rollback_test() {
+        let mut rb = RingBuffer::with_capacity(4);
+        rb.extend(0..3);
+        let mut tx = rb.begin();
+        tx.extend(3..6);
+        assert_eq!(tx.len(), 3);
+        assert_eq!(tx.buffer().len(), 3);
+        tx.rollback();
+        assert_eq!(rb.iter().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+        let mut tx = rb.begin();
+        tx.push(3);
+        tx.push(4);
+        assert_eq!(tx.commit(), [0]);
+        assert_eq!(rb.iter().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn panic_test() {
+        let mut rb = RingBuffer::with_capacity(4);
+        rb.push("header".to_string());
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut tx = rb.begin();
+            tx.push("part 1".to_string());
+            panic!("crashed before part 2");
+        }));
+        assert!(result.is_err());
+        assert_eq!(rb.iter().collect::<Vec<_>>(), ["header"]);
+    }
+}
@@ -0,0 +1,211 @@
+//! A triple buffer: a single slot that the writer keeps overwriting and the
+//! reader only ever sees the latest complete value of.
+//!
+//! Writer and reader each own one of three buffers; the third sits in the
+//! middle. Publishing swaps the writer's buffer with the middle one, and a
+//! read that finds a fresh middle buffer swaps it with the reader's. Both
+//! sides are wait-free: one atomic swap each, no locks and no retries.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use crate::sync::{Arc, AtomicU64, ConstPtr, MutPtr, Ordering, UnsafeCell};
+
+const INDEX: u64 = 0b011;
+const FRESH: u64 = 0b100; // The middle buffer holds a value the reader has not seen
+
+struct Shared<T> {
+    buffers: [UnsafeCell<T>; 3],
+    middle: AtomicU64, // Index of the middle buffer, plus `FRESH`
+}
+
+// Each buffer is only touched by the side that currently owns it, as decided
+// by the swaps of `middle`.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+pub struct TripleBuffer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone> TripleBuffer<T> {
+    /// Starts all three buffers out as copies of `initial`.
+    pub fn new(initial: T) -> Self {
+        let buffers = [
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial),
+        ];
+        TripleBuffer {
+            shared: Arc::new(Shared {
+                buffers,
+                middle: AtomicU64::new(2),
+            }),
+        }
+    }
+}
+
+impl<T> TripleBuffer<T> {
+    pub fn split(self) -> (Writer<T>, Reader<T>) {
+        let writer = Writer {
+            shared: Arc::clone(&self.shared),
+            index: 0,
+        };
+        let reader = Reader {
+            shared: self.shared,
+            index: 1,
+        };
+        (writer, reader)
+    }
+}
+
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+    index: u64,
+}
+
+impl<T> Writer<T> {
+    /// Publishes `value`, replacing whatever the reader has not picked up yet.
+    pub fn write(&mut self, value: T) {
+        *self.input_mut() = value;
+        self.publish();
+    }
+
+    /// The writer's own buffer, to update in place before `publish`. It holds
+    /// an older value, not necessarily the last one written.
+    pub fn input_mut(&mut self) -> Input<'_, T> {
+        Input {
+            ptr: self.shared.buffers[self.index as usize].get_mut(),
+            _writer: PhantomData,
+        }
+    }
+
+    /// Hands the writer's buffer to the reader as the latest value.
+    pub fn publish(&mut self) {
+        let old = self
+            .shared
+            .middle
+            .swap(self.index | FRESH, Ordering::AcqRel);
+        self.index = old & INDEX;
+    }
+}
+
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+    index: u64,
+}
+
+impl<T> Reader<T> {
+    /// Whether a value was published since the last `read`.
+    pub fn updated(&self) -> bool {
+        self.shared.middle.load(Ordering::Relaxed) & FRESH != 0
+    }
+
+    /// The latest published value, or the one returned last time if nothing
+    /// was published since.
+    pub fn read(&mut self) -> Output<'_, T> {
+        if self.updated() {
+            let old = self.shared.middle.swap(self.index, Ordering::AcqRel);
+            self.index = old & INDEX;
+        }
+        Output {
+            ptr: self.shared.buffers[self.index as usize].get(),
+            _reader: PhantomData,
+        }
+    }
+}
+
+/// The writer's buffer, borrowed by `Writer::input_mut`.
+pub struct Input<'a, T> {
+    ptr: MutPtr<T>,
+    _writer: PhantomData<&'a mut Writer<T>>,
+}
+
+impl<'a, T> Deref for Input<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.deref() }
+    }
+}
+
+impl<'a, T> DerefMut for Input<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.deref() }
+    }
+}
+
+/// The reader's buffer, borrowed by `Reader::read`.
+pub struct Output<'a, T> {
+    ptr: ConstPtr<T>,
+    _reader: PhantomData<&'a mut Reader<T>>,
+}
+
+impl<'a, T> Deref for Output<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.deref() }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn latest_test() {
+        let (mut writer, mut reader) = TripleBuffer::new(0).split();
+        assert!(!reader.updated());
+        assert_eq!(*reader.read(), 0);
+        writer.write(1);
+        writer.write(2);
+        assert!(reader.updated());
+        assert_eq!(*reader.read(), 2);
+        assert_eq!(*reader.read(), 2);
+        *writer.input_mut() = 3;
+        writer.publish();
+        assert_eq!(*reader.read(), 3);
+    }
+
+    #[test]
+    fn threads_test() {
+        let (mut writer, mut reader) = TripleBuffer::new(vec![0; 16]).split();
+        let handle = thread::spawn(move || {
+            for i in 1..=10_000 {
+                writer.input_mut().iter_mut().for_each(|x| *x = i);
+                writer.publish();
+            }
+        });
+        let mut last = 0;
+        while last < 10_000 {
+            let value = reader.read();
+            // A torn value would mix two writes
+            assert!(value.iter().all(|&x| x == value[0]));
+            assert!(value[0] >= last);
+            last = value[0];
+        }
+        handle.join().unwrap();
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::thread;
+
+    #[test]
+    fn publish_test() {
+        loom::model(|| {
+            let (mut writer, mut reader) = TripleBuffer::new(0).split();
+            let handle = thread::spawn(move || {
+                writer.write(1);
+                writer.write(2);
+            });
+            let seen = *reader.read();
+            handle.join().unwrap();
+            assert!(*reader.read() >= seen);
+            assert_eq!(*reader.read(), 2);
+        });
+    }
+}
@@ -0,0 +1,38 @@
+use crate::sync::{park_timeout, spin_loop, yield_now};
+use std::time::Duration;
+
+const SPIN_LIMIT: u32 = 64;
+
+/// How a lock-free buffer waits for the other side to make progress.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WaitStrategy {
+    /// Busy-spin: the lowest latency, at the cost of a whole core.
+    Spin,
+    /// Spin for a short while, then yield the time slice on every retry.
+    #[default]
+    SpinThenYield,
+    /// Spin for a short while, then park the thread for up to the given
+    /// duration between retries.
+    Park(Duration),
+}
+
+impl WaitStrategy {
+    /// Retries `attempt` until it succeeds.
+    pub(crate) fn wait_until<R, F: FnMut() -> Option<R>>(self, mut attempt: F) -> R {
+        let mut spins = 0;
+        loop {
+            if let Some(result) = attempt() {
+                return result;
+            }
+            match self {
+                WaitStrategy::Spin => spin_loop(),
+                _ if spins < SPIN_LIMIT => {
+                    spins += 1;
+                    spin_loop();
+                }
+                WaitStrategy::SpinThenYield => yield_now(),
+                WaitStrategy::Park(timeout) => park_timeout(timeout),
+            }
+        }
+    }
+}
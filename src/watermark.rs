@@ -0,0 +1,173 @@
+use crate::ringbuffer::{RBRefIter, RingBuffer};
+
+/// Which watermark the occupancy of a `WatermarkRingBuffer` just crossed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Watermark {
+    /// The length rose to the high watermark: time to apply backpressure.
+    High,
+    /// The length fell back to the low watermark: time to release it.
+    Low,
+}
+
+/// A ring buffer that reports when its length crosses a high and a low
+/// watermark, e.g. to pause and resume upstream producers.
+///
+/// The watermarks work as a hysteresis: after `High` is reported, nothing is
+/// reported again until the length drops to the low watermark, so a length
+/// that hovers around one threshold does not flap. `backpressure` tells the
+/// current state for code that polls instead of using the callback.
+pub struct WatermarkRingBuffer<T, F: FnMut(Watermark)> {
+    buffer: RingBuffer<T>,
+    low: usize,
+    high: usize,
+    backpressure: bool,
+    on_cross: F,
+}
+
+impl<T, F: FnMut(Watermark)> WatermarkRingBuffer<T, F> {
+    /// # Panics
+    ///
+    /// Unless `low < high <= cap`.
+    pub fn with_capacity(cap: usize, low: usize, high: usize, on_cross: F) -> Self {
+        assert!(low < high && high <= cap, "need low < high <= capacity");
+        WatermarkRingBuffer {
+            buffer: RingBuffer::with_capacity(cap),
+            low,
+            high,
+            backpressure: false,
+            on_cross,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    /// The low and high watermarks.
+    pub fn watermarks(&self) -> (usize, usize) {
+        (self.low, self.high)
+    }
+
+    /// Moves the watermarks; a crossing they cause is reported right away.
+    ///
+    /// # Panics
+    ///
+    /// Unless `low < high <= capacity`.
+    pub fn set_watermarks(&mut self, low: usize, high: usize) {
+        assert!(
+            low < high && high <= self.capacity(),
+            "need low < high <= capacity"
+        );
+        self.low = low;
+        self.high = high;
+        self.check();
+    }
+
+    /// Whether the high watermark was reached and the length has not yet
+    /// dropped back to the low one.
+    pub fn backpressure(&self) -> bool {
+        self.backpressure
+    }
+
+    /// Returns the oldest element if it had to be overwritten.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        let evicted = self.buffer.push(element);
+        self.check();
+        evicted
+    }
+
+    pub fn try_push(&mut self, element: T) -> Result<(), T> {
+        self.buffer.try_push(element)?;
+        self.check();
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let popped = self.buffer.pop();
+        self.check();
+        popped
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        let popped = self.buffer.pop_back();
+        self.check();
+        popped
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.buffer.front()
+    }
+
+    pub fn iter(&self) -> RBRefIter<'_, T> {
+        self.buffer.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.check();
+    }
+
+    fn check(&mut self) {
+        let len = self.buffer.len();
+        if !self.backpressure && len >= self.high {
+            self.backpressure = true;
+            (self.on_cross)(Watermark::High);
+        } else if self.backpressure && len <= self.low {
+            self.backpressure = false;
+            (self.on_cross)(Watermark::Low);
+        }
+    }
+}
+
+impl<T, F: FnMut(Watermark)> Extend<T> for WatermarkRingBuffer<T, F> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hysteresis_test() {
+        let mut crossings = Vec::new();
+        let mut rb = WatermarkRingBuffer::with_capacity(8, 2, 6, |w| crossings.push(w));
+        rb.extend(0..6);
+        assert!(rb.backpressure());
+        rb.pop();
+        rb.push(6); // Back at the high watermark, but already reported
+        for _ in 0..3 {
+            rb.pop();
+        }
+        assert!(rb.backpressure());
+        rb.pop();
+        assert!(!rb.backpressure());
+        rb.set_watermarks(0, 2);
+        rb.clear();
+        drop(rb);
+        assert_eq!(
+            crossings,
+            [
+                Watermark::High,
+                Watermark::Low,
+                Watermark::High,
+                Watermark::Low
+            ]
+        );
+    }
+}
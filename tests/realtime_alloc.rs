@@ -0,0 +1,60 @@
+//! Checks that `RtRingBuffer` never allocates once it is built. The counting
+//! allocator replaces the global one, so it gets this test binary to itself.
+
+use circus::realtime::RtRingBuffer;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// Counts per thread, so the test harness allocating alongside does not count.
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn no_allocation_test() {
+    for cap in [0, 1, 5, 8] {
+        let mut rb = RtRingBuffer::with_capacity(cap);
+        let mut out = [0u32; 3];
+        let before = allocations();
+        for i in 0..100 {
+            rb.push(i);
+            let _ = rb.try_push(i);
+            rb.extend_from_slice(&[i, i + 1, i + 2]);
+            rb.pop();
+            rb.pop_back();
+            rb.pop_into(&mut out);
+            let _ = (rb.front(), rb.back(), rb.get(i as usize));
+            let _ = rb.get_mut(0);
+            let _ = rb.iter().count() + rb.as_slices().0.len();
+            if i % 10 == 0 {
+                rb.clear();
+            }
+        }
+        assert_eq!(allocations(), before, "capacity {}", cap);
+    }
+}